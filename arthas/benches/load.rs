@@ -0,0 +1,52 @@
+#![cfg_attr(all(feature = "unstable", test), feature(test))]
+
+#[macro_use]
+extern crate serde_derive;
+#[macro_use]
+extern crate arthas_derive;
+extern crate rand;
+extern crate arthas;
+extern crate env_logger;
+
+#[path = "../tests/common/mod.rs"]
+pub mod common;
+#[path = "../tests/model/mod.rs"]
+pub mod model;
+
+#[cfg(all(feature = "unstable", test))]
+mod benches {
+    extern crate test;
+
+    use std::sync::{Once, ONCE_INIT};
+    use model::*;
+    use super::common;
+    use super::common::setup;
+
+    const ITEMS: usize = 10000;
+
+
+    fn load_setup() {
+        static ONCE: Once = ONCE_INIT;
+
+        ONCE.call_once(|| {
+            setup();
+
+            for i in 0..ITEMS {
+                Article::session()
+                    .insert(Article::new(common::random_string())
+                        .views(i)
+                        .content("Foobar! ".repeat(16)))
+                    .unwrap();
+            }
+
+            arthas::load::<Article>().unwrap();
+        });
+    }
+
+    #[bench]
+    fn bench_load(b: &mut test::Bencher) {
+        load_setup();
+
+        b.iter(|| arthas::load::<Article>().unwrap())
+    }
+}
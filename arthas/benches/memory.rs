@@ -17,10 +17,13 @@ pub mod model;
 mod benches {
     extern crate test;
 
+    use std::sync::atomic::{AtomicUsize, Ordering, ATOMIC_USIZE_INIT};
     use model::*;
     use super::common;
     use super::common::memory_setup;
 
+    static CREATED: AtomicUsize = ATOMIC_USIZE_INIT;
+
 
     #[bench]
     fn bench_a_insert_random(b: &mut test::Bencher) {
@@ -44,6 +47,31 @@ mod benches {
         })
     }
 
+    #[bench]
+    fn bench_a_insert_sorted(b: &mut test::Bencher) {
+        memory_setup();
+
+        b.iter(|| {
+            Post::session()
+                .insert(Post::new("Hello", CREATED.fetch_add(1, Ordering::SeqCst)))
+                .unwrap()
+        })
+    }
+
+    #[bench]
+    fn bench_sorted_lt_100(b: &mut test::Bencher) {
+        memory_setup();
+
+        b.iter(|| {
+            Post::session()
+                .field("created")
+                .lt(100)
+                .limit(100)
+                .find()
+                .unwrap()
+        })
+    }
+
     #[bench]
     fn bench_find(b: &mut test::Bencher) {
         memory_setup();
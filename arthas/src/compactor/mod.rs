@@ -0,0 +1,119 @@
+
+use std::io::prelude::*;
+use std::fs;
+use std::sync::RwLock;
+use memmap::{Mmap, Protection};
+use traits::Structure;
+use persistence::Persistence;
+use persistence::codec;
+use persistence::meta::Meta;
+use loader::{read_persistence, save_persistence, remove_old_persistence, fix_persistence_name,
+             read_header, is_data_headerless};
+use store::{persistences, get_compaction_threshold, is_compressed};
+use utils;
+use utils::crash;
+use error::Error;
+use utils::file::{get_data_path, get_compacting_path};
+
+
+pub fn compact<T: Structure>() -> Result<(), Error> {
+    compact_struct(&T::get_struct_name())
+}
+
+/// Compact when the dead bytes of the data file exceed the configured ratio,
+/// or the records or data file are of an older version.
+pub fn compact_if_needed(struct_name: &str) -> Result<(), Error> {
+    let needed = match read_persistence(struct_name)? {
+        Some(persistence) => {
+            persistence.get_dead_ratio() > get_compaction_threshold() ||
+            persistence.version != codec::RECORD_VERSION ||
+            is_data_headerless(struct_name)?
+        }
+        None => false,
+    };
+
+    if needed {
+        compact_struct(struct_name)?;
+    }
+
+    Ok(())
+}
+
+/// Copy the live records into a fresh data file in the current record version and
+/// compression, then swap the data file and index so `fix_persistence_name` can finish or
+/// undo a crash.
+pub fn compact_struct(struct_name: &str) -> Result<(), Error> {
+    let persistence_store_lock = persistences();
+    let mut persistence_store = persistence_store_lock.write().unwrap();
+
+    fix_persistence_name(struct_name)?;
+
+    let persistence = match read_persistence(struct_name)? {
+        Some(persistence) => persistence,
+        None => return Ok(()),
+    };
+
+    thread_trace!("compact {}, dead ratio: {}", struct_name, persistence.get_dead_ratio());
+
+    let mut metas = persistence.metas.iter().collect::<Vec<_>>();
+    metas.sort_by_key(|&(_, meta)| meta.offset());
+
+    let compress = is_compressed(struct_name);
+    let mut compacted = Persistence::new(persistence.struct_name.clone(),
+                                         persistence.field_int_map.clone());
+    let mut compacting_file = utils::file::open_with_write(get_compacting_path(struct_name))?;
+    compacting_file.write_all(&codec::encode_header(codec::DATA_MAGIC, struct_name))?;
+
+    if !metas.is_empty() {
+        let mut data_file = utils::file::open_data_with_read(struct_name)?;
+        let header = read_header(&mut data_file, codec::DATA_MAGIC, struct_name)?;
+
+        for (id, meta) in metas {
+            let mmap = Mmap::open_with_offset(&data_file,
+                                              Protection::Read,
+                                              header + meta.offset(),
+                                              meta.real())?;
+            let bytes: &[u8] = unsafe { mmap.as_slice() };
+
+            let moved = if persistence.version == compacted.version &&
+                           meta.is_compressed() == compress {
+                compacting_file.write_all(bytes)?;
+                meta.moved(compacted.size)
+            } else {
+                let value =
+                    codec::unpack_record(bytes, persistence.version, meta.is_compressed())?;
+                let (record, compressed) = codec::pack_record(&value, compacted.version, compress);
+                compacting_file.write_all(&record)?;
+
+                let mut moved = Meta::new(&record, compressed).to_simple();
+                moved.set_offset(compacted.size);
+                moved
+            };
+
+            compacted.size += moved.real();
+            compacted.metas.insert(id.clone(), moved);
+        }
+    }
+
+    compacting_file.sync_all()?;
+    crash::point("compact:data");
+
+    save_persistence(compacted.clone())?;
+    crash::point("compact:saving");
+
+    remove_old_persistence(struct_name)?;
+    utils::file::sync_dir()?;
+    crash::point("compact:remove");
+
+    fs::rename(get_compacting_path(struct_name), get_data_path(struct_name))?;
+    utils::file::sync_dir()?;
+    crash::point("compact:rename");
+
+    fix_persistence_name(struct_name)?;
+
+    if let Some(persistence) = persistence_store.get_mut(struct_name) {
+        *persistence = RwLock::new(compacted);
+    }
+
+    Ok(())
+}
@@ -1,9 +1,45 @@
 //! Config Arthas.
 
 use std::path::Path;
+use std::time::Duration;
 use store::config;
 
 
+/// When written log lines are synced to disk.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Durability {
+    /// Leave it to the operating system.
+    None,
+    /// Sync every write before it returns.
+    EveryWrite,
+    /// Sync the writes of all threads once per interval, writes return after their sync.
+    GroupCommit {
+        /// Time between syncs, read again before each sync.
+        interval: Duration,
+    },
+}
+
+/// What loading does with a record that fails its checksum or can not be decoded.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Corruption {
+    /// Return `Error::Corrupt`.
+    Fail,
+    /// Leave the record out and list it in the load report.
+    Skip,
+    /// Like `Skip`, and copy its bytes to `<struct>.quarantine/<id>` under the data path.
+    Quarantine,
+}
+
+/// Set how corrupt records are handled on load. Defaults to `Corruption::Fail`.
+pub fn corruption(corruption: Corruption) {
+    config().write().unwrap().corruption = corruption;
+}
+
+/// Set when writes are synced to disk. Defaults to `Durability::None`.
+pub fn durability(durability: Durability) {
+    config().write().unwrap().durability = durability;
+}
+
 /// Set `false` to disable persistence. Defaults to `true`.
 pub fn persistence(persistence: bool) {
     config().write().unwrap().persistence = persistence;
@@ -13,3 +49,31 @@ pub fn persistence(persistence: bool) {
 pub fn path<P: AsRef<Path>>(path: P) {
     config().write().unwrap().path = path.as_ref().to_owned();
 }
+
+/// Set how often expired items are removed in the background. Defaults to one second.
+pub fn sweep_interval(interval: Duration) {
+    config().write().unwrap().sweep_interval = interval;
+}
+
+/// Set the ratio of dead bytes in a data file that compacts it when loading or after the log
+/// is folded into it. Defaults to `0.5`.
+pub fn compaction_threshold(threshold: f64) {
+    config().write().unwrap().compaction_threshold = threshold;
+}
+
+/// Set the longest time writes stay only in the log before folded into the data file.
+/// Defaults to 60 seconds.
+pub fn checkpoint_interval(interval: Duration) {
+    config().write().unwrap().checkpoint_interval = interval;
+}
+
+/// Set the log size in bytes that folds the log into the data file. Defaults to 64 MiB.
+pub fn checkpoint_size(size: u64) {
+    config().write().unwrap().checkpoint_size = size;
+}
+
+/// Set `true` to compress the records of all structs, not only those with
+/// `#[arthas(compress)]`. Defaults to `false`.
+pub fn compression(compression: bool) {
+    config().write().unwrap().compression = compression;
+}
@@ -1,6 +1,7 @@
 
 use serde_json::{self, Value};
 use traits::FieldIntMap;
+use error::Error;
 use std::iter::IntoIterator;
 use bincode::SizeLimit;
 use bincode::serde::{deserialize, serialize};
@@ -158,8 +159,8 @@ pub fn bin_encode<T: Into<String>>(input: T) -> Vec<u8> {
 }
 
 #[inline]
-pub fn bin_decode<T: AsRef<[u8]>>(input: T) -> String {
-    deserialize(input.as_ref()).unwrap()
+pub fn bin_decode<T: AsRef<[u8]>>(input: T) -> Result<String, Error> {
+    deserialize(input.as_ref()).map_err(|err| Error::Corrupt(err.to_string()))
 }
 
 #[inline]
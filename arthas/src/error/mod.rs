@@ -19,5 +19,24 @@ quick_error! {
         FieldNotFound {
             display("Query field can not be not found in the struct.")
         }
+        /// Reading or writing a file failed.
+        Io(kind: ::std::io::ErrorKind, message: String) {
+            from(err: ::std::io::Error) -> (err.kind(), err.to_string())
+            display("I/O error: {}", message)
+        }
+        /// A persisted file can not be decoded.
+        Corrupt(message: String) {
+            display("Corrupt persistence: {}", message)
+        }
+        /// A persisted file was written in another format version.
+        #[allow(missing_docs)]
+        UnsupportedFormat { found: u64, expected: u64 } {
+            display("Unsupported format version {}, expected {}", found, expected)
+        }
+        /// Value of an unique field already exists.
+        #[allow(missing_docs)]
+        DuplicateKey { field: String, value: ::serde_json::Value } {
+            display("Duplicate key, field: {}, value: {}", field, value)
+        }
     }
 }
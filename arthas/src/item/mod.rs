@@ -46,3 +46,8 @@ fn create_id() -> String {
 pub fn get_len_field_int(field_int: &str) -> String {
     format!("len({})", field_int)
 }
+
+#[inline]
+pub fn get_compound_field_int(field_ints: &[FieldInt]) -> String {
+    format!("index({})", field_ints.join(","))
+}
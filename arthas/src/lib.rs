@@ -89,9 +89,13 @@
 //! Arthas will not automatically load persistence from disk, you have to load persistence yourself.
 //!
 //! ```html
-//! arthas::load::<Article>();  // Load `Article`'s persistence.
+//! arthas::load::<Article>().unwrap();  // Load `Article`'s persistence.
 //! ```
 //!
+//! Every file starts with a header holding its format version, files of another version fail
+//! with `Error::UnsupportedFormat`. Files written before headers are read as they are and
+//! rewritten in the current format by `load()`.
+//!
 //! # Update Structure
 //! Sometimes you want to update your structure. Like renaming or removing fields. Arthas will automatically remove and add fields, but you have to tell Arthas if you want to **rename** fields.
 //!
@@ -106,10 +110,175 @@
 //! }
 //! ```
 //!
+//! # Unique Fields
+//! Use `#[arthas(unique)]` on a field to reject items whose value of the field already exists.
+//! `insert()`, `update()` and `replace()` return `Error::DuplicateKey` and change nothing.
+//!
+//! ```html
+//! #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize, Arthas)]
+//! pub struct User {
+//!     pub _id: String,
+//!     #[arthas(unique)]
+//!     pub email: String,
+//! }
+//! ```
+//!
+//! # Indexes
+//! All fields are indexed by default. Use `#[arthas(no_index)]` on a field to skip its index, or
+//! on the struct to make unindexed the default and pick fields with `#[arthas(index)]`.
+//! Unindexed fields can still be queried and sorted, the items are scanned instead.
+//!
+//! ```html
+//! #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize, Arthas)]
+//! #[arthas(no_index)]
+//! pub struct Article {
+//!     pub _id: String,
+//!     #[arthas(index)]
+//!     pub title: String,
+//!     pub content: String,
+//! }
+//! ```
+//!
+//! A compound index orders several fields together. It is used when the query compares its
+//! leading fields with `eq()`, followed by a range or an order on the next field.
+//!
+//! ```html
+//! #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize, Arthas)]
+//! #[arthas(index = "author, created")]
+//! pub struct Post {
+//!     pub _id: String,
+//!     pub author: String,
+//!     pub created: usize,
+//! }
+//!
+//! Post::session().field("author").eq("fengcen").field("created").gt(time).desc("created").find();
+//! ```
+//!
+//! Fields only compared with `eq()` can use a hash index with `#[arthas(index = "hash")]`.
+//! Other comparisions on such fields scan the items.
+//!
+//! # Collation
+//! Strings are compared byte by byte. `#[arthas(collate = "nocase")]` compares a field ignoring
+//! case, `#[arthas(collate = "unicode")]` orders it by letters ignoring case and accents, while
+//! `eq()` still requires the same string, composed or decomposed. `ieq()` compares any string
+//! field ignoring case.
+//!
+//! ```html
+//! #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize, Arthas)]
+//! pub struct User {
+//!     pub _id: String,
+//!     #[arthas(unique, collate = "nocase")]
+//!     pub email: String,
+//!     #[arthas(collate = "unicode")]
+//!     pub name: String,
+//! }
+//!
+//! User::session().field("name").ieq("émile").asc("name").find();
+//! ```
+//!
+//! # Full-text Search
+//! `#[arthas(text)]` keeps an inverted index of the field's words, lowercased and stemmed.
+//! `text()` finds items containing any of the words, ranked by relevance unless the query is
+//! ordered, and can be combined with other conditions.
+//!
+//! ```html
+//! #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize, Arthas)]
+//! pub struct Article {
+//!     pub _id: String,
+//!     #[arthas(text)]
+//!     pub title: String,
+//!     #[arthas(text)]
+//!     pub content: String,
+//! }
+//!
+//! Article::session().text("rust database").limit(10).find();
+//! ```
+//!
+//! # Expiry
+//! `#[arthas(ttl)]` marks a field holding the unix time in seconds an item expires at, and
+//! `#[arthas(ttl = "3600")]` a time the item expires an hour after. Zero never expires.
+//! Expired items are skipped by queries and removed in the background, see
+//! `config::sweep_interval()`.
+//!
+//! ```html
+//! #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize, Arthas)]
+//! pub struct Session {
+//!     pub _id: String,
+//!     pub token: String,
+//!     #[arthas(ttl = "3600")]
+//!     pub created: u64,
+//! }
+//! ```
+//!
+//! # Checkpoint
+//! Writes are appended to a log, which is folded into the data file in the background once
+//! it is older than `config::checkpoint_interval()` or larger than `config::checkpoint_size()`.
+//!
+//! # Durability
+//! Log lines are not synced to disk by default. `Durability::EveryWrite` syncs each write, and
+//! `Durability::GroupCommit` syncs the writes of all threads together once per interval.
+//!
+//! ```html
+//! arthas::config::durability(Durability::GroupCommit { interval: Duration::from_millis(10) });
+//! ```
+//!
+//! # Compaction
+//! Removed and shrunk items leave unused space in the data file. `load()` and each
+//! checkpoint rewrite the file when the unused ratio passes `config::compaction_threshold()`,
+//! and `compact()` does it on demand. A remove of more than that ratio of the items
+//! checkpoints at once.
+//!
+//! ```html
+//! arthas::compact::<Article>().unwrap();
+//! ```
+//!
+//! # Compression
+//! `#[arthas(compress)]` compresses the records of a struct in the data file, and
+//! `config::compression(true)` those of all structs. Records written before keep their
+//! encoding until the data file is compacted.
+//!
+//! ```html
+//! #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize, Arthas)]
+//! #[arthas(compress)]
+//! pub struct Article {
+//!     pub _id: String,
+//!     pub title: String,
+//!     pub content: String,
+//! }
+//! ```
+//!
+//! # Corruption
+//! Records and index files carry a CRC-32 checked on load. A corrupt index fails with
+//! `Error::Corrupt`, corrupt records too unless `config::corruption()` skips or quarantines
+//! them, in which case their ids are listed in the returned `Report`.
+//!
+//! ```html
+//! arthas::config::corruption(Corruption::Quarantine);
+//! let report = arthas::load::<Article>().unwrap();
+//! ```
+//!
+//! # Watch
+//! `watch()` returns a channel receiving `Event::Inserted`, `Event::Updated` and
+//! `Event::Removed` for items matching the query's conditions, once their change is done.
+//!
+//! ```html
+//! let receiver = Article::session().field("title").eq("Hello world!").watch().unwrap();
+//!
+//! for event in receiver {
+//!     match event {
+//!         Event::Inserted(item) => println!("inserted: {:?}", item),
+//!         Event::Updated { before, after } => println!("{:?} updated to {:?}", before, after),
+//!         Event::Removed(item) => println!("removed: {:?}", item),
+//!     }
+//! }
+//! ```
+//!
 #![deny(missing_docs)]
 
 #[cfg(test)]
 extern crate env_logger;
+#[cfg(test)]
+extern crate mktemp;
 
 #[macro_use]
 extern crate quick_error;
@@ -131,6 +300,9 @@ extern crate objectid;
 extern crate thread_id;
 extern crate chrono;
 extern crate vec_map;
+extern crate im;
+extern crate lz4_flex;
+extern crate unicode_normalization;
 
 
 #[macro_use]
@@ -142,10 +314,12 @@ mod persistence;
 mod store;
 mod utils;
 mod loader;
+mod compactor;
 mod query;
 mod error;
 mod tree;
 mod item;
+mod watch;
 
 #[doc(hidden)]
 pub mod traits;
@@ -154,15 +328,22 @@ pub mod prelude;
 pub use query::Query;
 pub use error::Error;
 pub use item::Id;
+pub use watch::Event;
+pub use loader::Report;
 
 #[doc(hidden)]
 pub mod types;
 pub mod config;
 
 
-/// Load persistence.
-pub fn load<T: traits::Structure>() {
-    loader::load::<T>();
+/// Load persistence, the report lists the corrupt records left out.
+pub fn load<T: traits::Structure>() -> Result<Report, Error> {
+    loader::load::<T>()
+}
+
+/// Rewrite the data file of `T` without the space of removed items.
+pub fn compact<T: traits::Structure>() -> Result<(), Error> {
+    compactor::compact::<T>()
 }
 
 /// Convert variable to `serde_json::Value`.
@@ -175,5 +356,7 @@ const DATA_EXTENSION: &'static str = "ar";
 const PERSISTENCE_EXTENSION: &'static str = "arx";
 const LOG_EXTENSION: &'static str = "arl";
 const SAVING_EXTENSION: &'static str = "saving";
+const COMPACTING_EXTENSION: &'static str = "compacting";
+const QUARANTINE_EXTENSION: &'static str = "quarantine";
 const DATA_DIR: &'static str = "arthas.ar";
 const BINENCODE: bool = true;
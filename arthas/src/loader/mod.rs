@@ -1,8 +1,9 @@
 
 use std::io::prelude::*;
-use std::str::from_utf8;
+use std::io::SeekFrom;
+use std::cmp;
 use std::str;
-use std::sync::{RwLock, RwLockWriteGuard};
+use std::sync::{Arc, RwLock};
 use std::io::BufReader;
 use std::io::BufRead;
 use std::fs::{self, File};
@@ -11,29 +12,56 @@ use serde_json::{self, Value};
 use to_value;
 use persistence::operation::Operation;
 use traits::{FieldIntMap, RenameMap};
-use encoder::{bin_encode, bin_decode};
 use traits::get_unique_int_str;
 use {utils, encoder};
 use memory::Memory;
 use traits::Structure;
 use persistence::Persistence;
-use config;
-use utils::file::{get_log_path, get_data_path, get_persistence_path, get_saving_path};
+use persistence::codec;
+use persistence::logger::Line;
+use error::Error;
+use utils::file::{get_log_path, get_data_path, get_persistence_path, get_saving_path,
+                  get_compacting_path};
 use persistence::meta::SimpleMeta;
-use BINENCODE;
-use store::{is_persistence, persistences, memories, config};
+use store::{persistences, memories, creating, get_memory, config, get_corruption,
+            register_compression};
+use config::Corruption;
+use compactor;
+use item::Id;
+use utils::crc32;
+use utils::crash;
+use utils::file::get_quarantine_path;
 
 
-pub fn load<T: Structure>() {
-    let struct_name = T::get_struct_name();
+/// Ends an index file, followed by the checksum of the bytes before it.
+const INDEX_CHECKSUM_MAGIC: &'static [u8] = b"ACRC";
+
+
+/// Result of loading.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Report {
+    /// Ids of the corrupt records skipped or quarantined, see `config::corruption()`.
+    pub corrupt: Vec<Id>,
+}
 
-    fix_persistence_name(&struct_name);
-    check_log_and_persistence(&struct_name);
 
-    if utils::file::exists(get_log_path(&struct_name)) {
-        write_log(&struct_name);
+pub fn load<T: Structure>() -> Result<Report, Error> {
+    let struct_name = T::get_struct_name();
+    register_compression::<T>();
+
+    {
+        let _persistence_store = persistences().write().unwrap();
+        checkpoint(&struct_name)?;
     }
 
+    let report = load_memory::<T>(&struct_name)?;
+    compactor::compact_if_needed(&struct_name)?;
+    Ok(report)
+}
+
+/// Corrupt records left out of the memory are removed from the index too.
+fn load_memory<T: Structure>(struct_name: &str) -> Result<Report, Error> {
+    let struct_name = struct_name.to_owned();
     let field_int_map = T::get_field_int_map();
     let rename_map = T::get_rename_map();
     let persistence_store_lock = persistences();
@@ -46,50 +74,164 @@ pub fn load<T: Structure>() {
             .write()
             .unwrap();
 
-    let memory_store_lock = memories();
-    let mut memory_store = memory_store_lock.write().unwrap();
-    let memory = memory_store.entry(struct_name.clone())
-        .or_insert_with(|| RwLock::new(Memory::new::<T>()))
-        .write()
-        .unwrap();
+    let memory = {
+        let _creating = creating().lock().unwrap();
+
+        // A memory created for other fields is rebuilt, like its persistence is migrated.
+        match get_memory(&struct_name) {
+            Some(ref memory) if *memory.field_int_map == field_int_map => memory.clone(),
+            _ => {
+                let memory = Arc::new(Memory::new::<T>()?);
+                memories().write().unwrap().insert(struct_name.clone(), memory.clone());
+                memory
+            }
+        }
+    };
 
+    let mut report = Report::default();
     let persistence_option = load_persistence(&struct_name,
                                               &field_int_map,
                                               &rename_map,
-                                              &to_value(T::new_deep_empty()));
+                                              &to_value(T::new_deep_empty()),
+                                              &mut report)?;
 
     if persistence_option.is_some() {
         *persistence = persistence_option.unwrap();
-        load_data(memory, &persistence, &struct_name, &T::get_field_int_map());
+        let loaded = load_data(&memory, &mut persistence, &struct_name, &T::get_field_int_map())?;
+
+        if !loaded.corrupt.is_empty() {
+            replace_persistence(persistence.clone())?;
+        }
+
+        report.corrupt.extend(loaded.corrupt);
     } else {
-        create_persistence(&struct_name, &field_int_map);
+        create_persistence(&struct_name, &field_int_map)?;
     }
 
     config();
+    Ok(report)
+}
+
+/// Fold the log into the data file and empty it, the caller holds the persistence store lock.
+pub fn checkpoint(struct_name: &str) -> Result<(), Error> {
+    fix_persistence_name(struct_name)?;
+    check_log_and_persistence(struct_name)?;
+    repair_log(struct_name)?;
+
+    if get_log_size(struct_name)? > 0 {
+        write_log(struct_name)?;
+    }
+
+    Ok(())
+}
+
+/// Cut a line torn by a crash in the middle of a write off the end of the log.
+pub fn repair_log(struct_name: &str) -> Result<(), Error> {
+    if !utils::file::exists(get_log_path(struct_name)) {
+        return Ok(());
+    }
+
+    let mut file = utils::file::open_log_file(struct_name)?;
+    let header = ensure_header(&mut file, codec::LOG_MAGIC, struct_name)?;
+    let mut bytes = Vec::new();
+    file.seek(SeekFrom::Start(header as u64))?;
+    file.read_to_end(&mut bytes)?;
+
+    if bytes.is_empty() || bytes.ends_with(b"\n") {
+        return Ok(());
+    }
+
+    let start = bytes.iter().rposition(|&byte| byte == b'\n').map_or(0, |index| index + 1);
+
+    if serde_json::from_slice::<Line>(&bytes[start..]).is_ok() {
+        file.write_all(b"\n")?;
+    } else {
+        warn!("{} log ends with a torn line of {} bytes, truncated.",
+              struct_name,
+              bytes.len() - start);
+        file.set_len((header + start) as u64)?;
+    }
+
+    Ok(())
+}
+
+/// Size of the lines in the log.
+pub fn get_log_size(struct_name: &str) -> Result<u64, Error> {
+    let path = get_log_path(struct_name);
+    if !utils::file::exists(&path) {
+        return Ok(0);
+    }
+
+    let mut file = utils::file::open_with_read(path)?;
+    let header = read_header(&mut file, codec::LOG_MAGIC, struct_name)?;
+    Ok(file.metadata()?.len() - header as u64)
+}
+
+/// Whether the data file was written before files had headers.
+pub fn is_data_headerless(struct_name: &str) -> Result<bool, Error> {
+    let path = get_data_path(struct_name);
+    if !utils::file::exists(&path) {
+        return Ok(false);
+    }
+
+    let mut file = utils::file::open_with_read(path)?;
+    Ok(file.metadata()?.len() > 0 && read_header(&mut file, codec::DATA_MAGIC, struct_name)? == 0)
+}
+
+/// Length of the header the file starts with, 0 for files written before headers.
+pub fn read_header(file: &mut File, magic: &[u8], struct_name: &str) -> Result<usize, Error> {
+    let bytes = read_start(file, codec::encode_header(magic, struct_name).len())?;
+    Ok(codec::decode_header(&bytes, magic, struct_name)?.unwrap_or(0))
+}
+
+/// Write the header into a file that is empty or ends within it, then read it.
+pub fn ensure_header(file: &mut File, magic: &[u8], struct_name: &str) -> Result<usize, Error> {
+    let header = codec::encode_header(magic, struct_name);
+    let bytes = read_start(file, header.len())?;
+
+    if bytes.len() < header.len() && header.starts_with(&bytes) {
+        file.set_len(0)?;
+        file.seek(SeekFrom::Start(0))?;
+        file.write_all(&header)?;
+        file.sync_data()?;
+        return Ok(header.len());
+    }
+
+    Ok(codec::decode_header(&bytes, magic, struct_name)?.unwrap_or(0))
+}
+
+fn read_start(file: &mut File, len: usize) -> Result<Vec<u8>, Error> {
+    let mut bytes = Vec::new();
+    file.seek(SeekFrom::Start(0))?;
+    let reader: &File = file;
+    reader.take(len as u64).read_to_end(&mut bytes)?;
+    Ok(bytes)
 }
 
 pub fn persistence_exits(struct_name: &str) -> bool {
     get_persistence_path(struct_name).is_file()
 }
 
-pub fn create_persistence(struct_name: &str, field_int_map: &FieldIntMap) {
-    save_persistence(Persistence::new(struct_name.to_owned(), field_int_map.clone()));
-    fix_persistence_name(struct_name);
+pub fn create_persistence(struct_name: &str, field_int_map: &FieldIntMap) -> Result<(), Error> {
+    save_persistence(Persistence::new(struct_name.to_owned(), field_int_map.clone()))?;
+    fix_persistence_name(struct_name)
 }
 
+/// Corrupt records left out when migrating to new fields are added to the report.
 pub fn load_persistence(struct_name: &str,
                         field_int_map: &FieldIntMap,
                         rename_map: &RenameMap,
-                        empty_value: &Value)
-                        -> Option<Persistence> {
+                        empty_value: &Value,
+                        report: &mut Report)
+                        -> Result<Option<Persistence>, Error> {
     let persistence_path = get_persistence_path(struct_name);
     if !utils::file::exists(&persistence_path) {
-        return None;
+        return Ok(None);
     }
 
-    let persistence_option = read_persistence(struct_name);
+    let persistence_option = read_persistence(struct_name)?;
     if persistence_option.is_none() {
-        return None;
+        return Ok(None);
     }
 
     let mut persistence = persistence_option.unwrap();
@@ -99,111 +241,221 @@ pub fn load_persistence(struct_name: &str,
                       struct_name,
                       field_int_map,
                       rename_map,
-                      empty_value);
-        persistence = read_persistence(struct_name).unwrap();
+                      empty_value,
+                      report)?;
+        persistence = read_persistence(struct_name)?
+            .ok_or_else(|| Error::Corrupt("index not found after updating schema".to_owned()))?;
     }
 
-    Some(persistence)
-
-
+    Ok(Some(persistence))
 }
 
-fn read_persistence(struct_name: &str) -> Option<Persistence> {
-    let file = utils::file::open_index_with_read(struct_name);
+pub fn read_persistence(struct_name: &str) -> Result<Option<Persistence>, Error> {
+    let file = utils::file::open_index_with_read(struct_name)?;
     let mut buf = Vec::new();
 
     if file.is_some() {
-        file.unwrap().read_to_end(&mut buf).unwrap();
+        file.unwrap().read_to_end(&mut buf)?;
+    }
+
+    if buf.len() >= 8 && &buf[buf.len() - 8..buf.len() - 4] == INDEX_CHECKSUM_MAGIC {
+        let len = buf.len() - 8;
+        if crc32::checksum(&buf[..len]) != read_u32(&buf[len + 4..]) {
+            return Err(Error::Corrupt("index checksum mismatch".to_owned()));
+        }
+
+        buf.truncate(len);
     }
 
     if buf.is_empty() {
-        None
+        Ok(None)
     } else {
-        match serde_json::from_str::<Persistence>(&if BINENCODE {
-            bin_decode(&buf)
-        } else {
-            from_utf8(&buf).unwrap().to_owned()
-        }) {
-            Ok(persistence) => Some(persistence),
-            Err(err) => panic!("Invalid persistence index: {:?}", err),
-        }
+        Ok(Some(codec::decode_index(&buf, struct_name)?))
     }
 }
 
-pub fn load_data(mut memory: RwLockWriteGuard<Memory>,
-                 persistence: &Persistence,
+/// Corrupt records are left out of the persistence unless the policy is `Corruption::Fail`.
+pub fn load_data(memory: &Memory,
+                 persistence: &mut Persistence,
                  struct_name: &str,
-                 field_int_map: &FieldIntMap) {
-    let path = get_data_path(struct_name);
-    let enable_backup = is_persistence();
-    config::persistence(false);
+                 field_int_map: &FieldIntMap)
+                 -> Result<Report, Error> {
+    let mut report = Report::default();
 
-    if utils::file::exists(&path) {
-        let file = utils::file::open_data_with_read(struct_name);
-        if to_value(&persistence.field_int_map).to_string() != to_value(field_int_map).to_string() {
-            panic!("Incompatible schema!");
-        }
+    if !utils::file::exists(get_data_path(struct_name)) {
+        return Ok(report);
+    }
 
-        for (id, meta) in &persistence.metas {
-            let mmap = Mmap::open_with_offset(&file, Protection::Read, meta.offset(), meta.real())
-                .unwrap();
-
-            let bytes: &[u8] = unsafe { mmap.as_slice() };
-            let value = serde_json::from_str::<Value>(&if BINENCODE {
-                    bin_decode(bytes)
-                } else {
-                    from_utf8(bytes).unwrap().to_owned()
-                })
-                .unwrap();
-
-            memory.insert_encoded_value(id.to_owned(), value).unwrap();
+    let mut file = utils::file::open_data_with_read(struct_name)?;
+    let header = read_header(&mut file, codec::DATA_MAGIC, struct_name)?;
+    if to_value(&persistence.field_int_map).to_string() != to_value(field_int_map).to_string() {
+        return Err(Error::Corrupt("incompatible schema".to_owned()));
+    }
+
+    let mut values = Vec::new();
+
+    for (id, meta) in &persistence.metas {
+        match read_record(&file, header, meta, persistence.version) {
+            Ok(value) => values.push((id.to_owned(), value)),
+            Err(Error::Corrupt(message)) => {
+                skip_corrupt(struct_name, id, &message, &file, header, meta, &mut report)?
+            }
+            Err(err) => return Err(err),
         }
     }
 
-    config::persistence(enable_backup);
+    memory.insert_encoded_values(values)?;
+
+    for id in &report.corrupt {
+        persistence.delete(Operation::new().id(id.to_owned()).delete())?;
+    }
+
+    Ok(report)
+}
+
+/// Records are placed after the header of the data file.
+fn read_record(file: &File,
+               header: usize,
+               meta: &SimpleMeta,
+               version: u32)
+               -> Result<Value, Error> {
+    let offset = header + meta.offset();
+    if (offset + meta.real()) as u64 > file.metadata()?.len() {
+        return Err(Error::Corrupt("record is out of the data file".to_owned()));
+    }
+
+    let mmap = Mmap::open_with_offset(file, Protection::Read, offset, meta.real())?;
+    let bytes: &[u8] = unsafe { mmap.as_slice() };
+
+    if !meta.verify(bytes) {
+        return Err(Error::Corrupt("checksum mismatch".to_owned()));
+    }
+
+    codec::unpack_record(bytes, version, meta.is_compressed())
+}
+
+/// Fail on a corrupt record with `Corruption::Fail`, otherwise leave it out and report it.
+fn skip_corrupt(struct_name: &str,
+                id: &str,
+                message: &str,
+                file: &File,
+                header: usize,
+                meta: &SimpleMeta,
+                report: &mut Report)
+                -> Result<(), Error> {
+    let corruption = get_corruption();
+    if corruption == Corruption::Fail {
+        return Err(Error::Corrupt(format!("record {}: {}", id, message)));
+    }
+
+    error!("{} record {} is corrupt: {}", struct_name, id, message);
+
+    if corruption == Corruption::Quarantine {
+        quarantine(struct_name, id, file, header + meta.offset(), meta.real())?;
+    }
+
+    report.corrupt.push(id.to_owned());
+    Ok(())
 }
 
+/// Copy what is left of the record to the quarantine directory.
+fn quarantine(struct_name: &str,
+              id: &str,
+              file: &File,
+              offset: usize,
+              real: usize)
+              -> Result<(), Error> {
+    let len = file.metadata()?.len() as usize;
+    let start = cmp::min(offset, len);
+    let end = cmp::min(offset + real, len);
+    let mut bytes = vec![0; end - start];
+
+    let mut reader = file.try_clone()?;
+    reader.seek(SeekFrom::Start(start as u64))?;
+    reader.read_exact(&mut bytes)?;
+
+    let path = get_quarantine_path(struct_name);
+    fs::create_dir_all(&path)?;
+    utils::file::open_with_write(path.join(id))?.write_all(&bytes)?;
+    Ok(())
+}
+
+fn read_u32(bytes: &[u8]) -> u32 {
+    bytes.iter().take(4).enumerate().fold(0, |value, (index, &byte)| {
+        value | ((byte as u32) << (index * 8))
+    })
+}
 
+
+/// Migrate the records to the new fields into a fresh data file, then swap it in like
+/// `compactor::compact_struct` does. The old files are kept as `.backup`, corrupt records
+/// are left out by the corruption policy.
 fn update_schema(persistence: &Persistence,
                  struct_name: &str,
                  field_int_map: &FieldIntMap,
                  rename_map: &RenameMap,
-                 empty_value: &Value) {
-    let old_data_path = get_data_path(struct_name);
-    let old_data_index_path = get_persistence_path(struct_name);
-    let backup_data_path = format!("{}.backup", old_data_path.display());
-    let backup_data_index_path = format!("{}.backup", old_data_index_path.display());
-    fs::rename(&old_data_path, &backup_data_path).unwrap();
-    fs::rename(&old_data_index_path, &backup_data_index_path).unwrap();
-
-    let old_file = utils::file::open_with_read(backup_data_path);
-    let mut new_persistence = Persistence::new(struct_name.to_owned(), field_int_map.clone());
-
-    for meta in persistence.metas.values() {
-        let mmap = Mmap::open_with_offset(&old_file, Protection::Read, meta.offset(), meta.real())
-            .unwrap();
-        let bytes: &[u8] = unsafe { mmap.as_slice() };
-        let mut value = serde_json::from_str::<Value>(&if BINENCODE {
-                bin_decode(bytes)
-            } else {
-                from_utf8(bytes).unwrap().to_owned()
-            })
-            .unwrap();
+                 empty_value: &Value,
+                 report: &mut Report)
+                 -> Result<(), Error> {
+    let data_path = get_data_path(struct_name);
+    let persistence_path = get_persistence_path(struct_name);
+    fs::copy(&persistence_path, format!("{}.backup", persistence_path.display()))?;
+
+    let mut migrated = Persistence::new(struct_name.to_owned(), field_int_map.clone());
+    let mut compacting_file = utils::file::open_with_write(get_compacting_path(struct_name))?;
+    compacting_file.write_all(&codec::encode_header(codec::DATA_MAGIC, struct_name))?;
 
-        fix_value(&mut value, rename_map, empty_value, field_int_map);
+    if utils::file::exists(&data_path) {
+        fs::copy(&data_path, format!("{}.backup", data_path.display()))?;
+        let mut data_file = utils::file::open_with_read(&data_path)?;
+        let header = read_header(&mut data_file, codec::DATA_MAGIC, struct_name)?;
 
-        new_persistence.insert(Operation::new()
-            .value(value)
-            .insert());
+        for (id, meta) in &persistence.metas {
+            let fixed = read_record(&data_file, header, meta, persistence.version)
+                .and_then(|mut value| {
+                    fix_value(&mut value, rename_map, empty_value, field_int_map)?;
+                    Ok(value)
+                });
+
+            let value = match fixed {
+                Ok(value) => value,
+                Err(Error::Corrupt(message)) => {
+                    skip_corrupt(struct_name, id, &message, &data_file, header, meta, report)?;
+                    continue;
+                }
+                Err(err) => return Err(err),
+            };
+
+            // Nothing is deleted in the new index, so every record is appended.
+            let (_, _, bytes) = migrated.insert(Operation::new()
+                    .id(id.to_owned())
+                    .value(value)
+                    .insert())?;
+            compacting_file.write_all(&bytes)?;
+        }
     }
+
+    compacting_file.sync_all()?;
+    save_persistence(migrated)?;
+    remove_old_persistence(struct_name)?;
+    utils::file::sync_dir()?;
+
+    fs::rename(get_compacting_path(struct_name), &data_path)?;
+    utils::file::sync_dir()?;
+
+    fix_persistence_name(struct_name)
 }
 
+/// Records that are not an item are corrupt.
 fn fix_value(value: &mut Value,
              rename_map: &RenameMap,
              empty_value: &Value,
-             field_int_map: &FieldIntMap) {
+             field_int_map: &FieldIntMap)
+             -> Result<(), Error> {
     let integer_map = utils::hash_map::revert(field_int_map.clone());
-    let object = value.as_object_mut().unwrap().get_mut("item").unwrap().as_object_mut().unwrap();
+    let object = value.get_mut("item")
+        .and_then(Value::as_object_mut)
+        .ok_or_else(|| Error::Corrupt("record is not an item".to_owned()))?;
     let empty_value = encoder::encode(empty_value, field_int_map);
     let empty_object = empty_value.as_object().unwrap();
     let integers = object.keys().map(|v| v.to_owned()).collect::<Vec<_>>();
@@ -231,82 +483,146 @@ fn fix_value(value: &mut Value,
                           empty_object.get(integer).cloned().unwrap());
         }
     }
+
+    Ok(())
 }
 
-fn fix_persistence_name(struct_name: &str) {
+/// Bring the files back to a consistent state after a crash. Folding the log goes through
+///
+/// 1. records written past the size of the index or into its holes, unseen by the index;
+/// 2. the new index written to `.saving`;
+/// 3. `.saving` renamed over `.arx`, the commit point;
+/// 4. the log truncated.
+///
+/// Compaction goes through
+///
+/// 1. live records copied to `.compacting`;
+/// 2. the new index written to `.saving`;
+/// 3. `.arx` removed, the commit point;
+/// 4. `.compacting` renamed to `.ar`;
+/// 5. `.saving` renamed to `.arx`.
+///
+/// Every step is synced before the next. While `.arx` exists, a `.saving` or `.compacting`
+/// is from before a commit point and removed, `write_log` cuts records the index does not
+/// see, and the log is folded again, which is harmless as its lines apply by id.
+/// Without `.arx`, compaction is finished from its step 4.
+pub fn fix_persistence_name(struct_name: &str) -> Result<(), Error> {
     let persistence_path = get_persistence_path(struct_name);
     let saving_path = get_saving_path(struct_name);
-    if utils::file::exists(&persistence_path) && utils::file::exists(&saving_path) {
-        fs::remove_file(saving_path).unwrap();
+    let compacting_path = get_compacting_path(struct_name);
+    if utils::file::exists(&persistence_path) {
+        if utils::file::exists(&saving_path) {
+            fs::remove_file(saving_path)?;
+        }
+
+        if utils::file::exists(&compacting_path) {
+            fs::remove_file(compacting_path)?;
+        }
     } else if utils::file::exists(&saving_path) {
-        fs::rename(saving_path, persistence_path).unwrap();
+        if utils::file::exists(&compacting_path) {
+            fs::rename(compacting_path, get_data_path(struct_name))?;
+        }
+
+        fs::rename(saving_path, persistence_path)?;
+        utils::file::sync_dir()?;
     }
+
+    Ok(())
 }
 
-fn check_log_and_persistence(struct_name: &str) {
+fn check_log_and_persistence(struct_name: &str) -> Result<(), Error> {
     if utils::file::exists(get_log_path(struct_name)) &&
        !utils::file::exists(get_persistence_path(struct_name)) {
-        panic!("log exists but index not found!");
+        return Err(Error::Corrupt("log exists but index not found".to_owned()));
     }
-}
 
-fn fix_file_length(struct_name: &str, size: usize) {
-    utils::file::open_data_or_create(struct_name).set_len(size as u64).unwrap();
+    Ok(())
 }
 
-fn write_log(struct_name: &str) {
-    let mut persistence = read_persistence(struct_name).unwrap();
-    fix_file_length(struct_name, persistence.size);
+fn write_log(struct_name: &str) -> Result<(), Error> {
+    let mut persistence = read_persistence(struct_name)?
+        .ok_or_else(|| Error::Corrupt("log exists but index not found".to_owned()))?;
+    let mut mmap_file = utils::file::open_data_or_create(struct_name)?;
+    let header = ensure_header(&mut mmap_file, codec::DATA_MAGIC, struct_name)?;
+    mmap_file.set_len((header + persistence.size) as u64)?;
 
-    let log_file = utils::file::open_log_file(struct_name);
+    let mut log_file = utils::file::open_log_file(struct_name)?;
+    let log_header = read_header(&mut log_file, codec::LOG_MAGIC, struct_name)?;
+    log_file.seek(SeekFrom::Start(log_header as u64))?;
     let file = BufReader::new(&log_file);
-    let mut append_file = utils::file::open_data_with_append(struct_name);
-    let mmap_file = utils::file::open_data_or_create(struct_name);
+    let mut append_file = utils::file::open_data_with_append(struct_name)?;
 
     for line_result in file.lines() {
-        let line = line_result.unwrap();
-        let info = persistence.write_log(line);
+        let line = line_result?;
+        let info = persistence.write_log(line)?;
         if info.is_some() {
             let (append, meta, bytes) = info.unwrap();
-            save_bytes(&mut append_file, &mmap_file, append, meta, bytes);
+            save_bytes(&mut append_file, &mmap_file, header, append, meta, bytes)?;
         }
     }
 
-    save_persistence(persistence);
-    remove_old_persistence(struct_name);
-    fix_persistence_name(struct_name);
-    remove_log_file(struct_name);
+    append_file.sync_all()?;
+    crash::point("log:data");
+
+    replace_persistence(persistence)?;
+    crash::point("log:index");
+
+    truncate_log_file(struct_name)
 }
 
-fn remove_log_file(struct_name: &str) {
-    fs::remove_file(get_log_path(struct_name)).unwrap();
+/// Loggers append to the same file, so it is emptied down to its header instead of removed.
+fn truncate_log_file(struct_name: &str) -> Result<(), Error> {
+    let mut file = utils::file::open_log_file(struct_name)?;
+    file.set_len(0)?;
+    ensure_header(&mut file, codec::LOG_MAGIC, struct_name)?;
+    file.sync_all()?;
+    Ok(())
 }
 
-fn save_persistence(persistence: Persistence) {
-    utils::file::open_with_write(get_saving_path(&persistence.struct_name))
-        .write_all(&if BINENCODE {
-            bin_encode(to_value(persistence).to_string())
-        } else {
-            to_value(persistence).to_string().as_bytes().to_owned()
-        })
-        .unwrap();
+/// Save the index and rename it over the old one in a single step.
+pub fn replace_persistence(persistence: Persistence) -> Result<(), Error> {
+    let struct_name = persistence.struct_name.clone();
+    save_persistence(persistence)?;
+    crash::point("index:saving");
+
+    fs::rename(get_saving_path(&struct_name), get_persistence_path(&struct_name))?;
+    utils::file::sync_dir()?;
+    Ok(())
+}
+
+pub fn save_persistence(persistence: Persistence) -> Result<(), Error> {
+    let mut bytes = codec::encode_index(&persistence);
+
+    let checksum = crc32::checksum(&bytes);
+    bytes.extend_from_slice(INDEX_CHECKSUM_MAGIC);
+    bytes.extend((0..4).map(|index| (checksum >> (index * 8)) as u8));
+
+    let mut file = utils::file::open_with_write(get_saving_path(&persistence.struct_name))?;
+    file.write_all(&bytes)?;
+    file.sync_all()?;
+    Ok(())
 }
 
-fn remove_old_persistence(struct_name: &str) {
-    fs::remove_file(get_persistence_path(struct_name)).unwrap();
+pub fn remove_old_persistence(struct_name: &str) -> Result<(), Error> {
+    fs::remove_file(get_persistence_path(struct_name))?;
+    Ok(())
 }
 
 fn save_bytes(append_file: &mut File,
               mmap_file: &File,
+              header: usize,
               append: bool,
               meta: SimpleMeta,
-              data: Vec<u8>) {
+              data: Vec<u8>)
+              -> Result<(), Error> {
     if append {
-        append_file.write_all(&data).unwrap();
+        append_file.write_all(&data)?;
     } else {
         let mut mmap =
-            Mmap::open_with_offset(mmap_file, Protection::ReadWrite, meta.offset(), meta.real())
-                .unwrap();
+            Mmap::open_with_offset(mmap_file,
+                                   Protection::ReadWrite,
+                                   header + meta.offset(),
+                                   meta.real())?;
         {
             let old_bytes: &mut [u8] = unsafe { mmap.as_mut_slice() };
             for (index, byte) in old_bytes.iter_mut().enumerate() {
@@ -314,4 +630,6 @@ fn save_bytes(append_file: &mut File,
             }
         }
     }
+
+    Ok(())
 }
@@ -1,64 +1,133 @@
 
-use std::sync::Mutex;
-use std::sync::atomic::{AtomicPtr, Ordering};
-use std::sync::Arc;
+mod snapshot;
+pub mod sweeper;
+
+pub use self::snapshot::Snapshot;
+
+use std::collections::HashSet;
+use std::sync::{Arc, RwLock, Mutex, MutexGuard};
+use std::sync::mpsc::{self, Sender, Receiver};
 use query::Query;
-use item::{ItemWrapper, Id, get_len_field_int};
+use item::{ItemWrapper, Id, FieldInt, get_len_field_int};
 use serde_json::Value;
 use to_value;
 use store;
-use traits::{Structure, FieldIntMap};
+use traits::{Structure, FieldIntMap, get_unique_int_str};
 use persistence::service::PersistenceService;
+use persistence::logger::create_insert_line;
+use persistence::checkpointer;
 use error::Error;
-use item::convertor::Convertor;
 use encoder;
-use tree::Tree;
+use tree::{self, Tree};
+use tree::rc::RcItem;
+use tree::math::Math;
+use tree::ttl;
+use tree::searcher::task::Task;
+use watch::{Event, Watcher};
 use query::QueryType;
 use num_cpus;
 use utils::hash_map::revert;
 use scoped_pool::Pool;
-use vec_map::VecMap;
 
 
+/// Items are split into shards by their ids, writers of different shards do not wait for
+/// each other.
+const SHARDS: usize = 16;
+
 pub struct Memory {
     pub field_int_map: Arc<FieldIntMap>,
     pub int_field_map: Arc<FieldIntMap>,
-    pub service: PersistenceService,
-    pub tree: Tree,
+    pub service: Mutex<PersistenceService>,
     pub is_one: bool,
-    pub pool: Pool,
+    pub pool: Arc<Pool>,
+    pub unique_fields: Vec<(String, FieldInt)>,
+    versions: RwLock<Vec<Arc<Tree>>>,
+    writers: Vec<Mutex<()>>,
+    unique: Mutex<()>,
+    template: Tree,
+    watchers: Mutex<Vec<Watcher>>,
+    workers: Mutex<Vec<Sender<()>>>,
+    checkpointer: Mutex<Option<Sender<()>>>,
+}
+
+/// Insert encoded before any lock is taken.
+#[derive(Debug)]
+struct Prepared {
+    id: Id,
+    encoded: Value,
+    rc_item: RcItem,
+    line: Option<String>,
 }
 
 impl Memory {
-    pub fn new<T: Structure>() -> Memory {
-        Memory {
+    pub fn new<T: Structure>() -> Result<Memory, Error> {
+        let struct_name = T::get_struct_name();
+        store::register_compression::<T>();
+        let service = PersistenceService::new::<T>()?;
+
+        let mut workers = Vec::new();
+
+        if T::get_ttl_field().is_some() {
+            workers.push(sweeper::spawn(struct_name.clone()));
+        }
+
+        let checkpointer = if store::is_persistence() {
+            Some(checkpointer::spawn(struct_name))
+        } else {
+            None
+        };
+
+        Ok(Memory {
             field_int_map: Arc::new(T::get_field_int_map()),
             int_field_map: Arc::new(revert(T::get_field_int_map())),
-            service: PersistenceService::new::<T>(),
-            tree: Tree::new::<T>(),
+            service: Mutex::new(service),
             is_one: T::is_one(),
-            pool: Pool::new(num_cpus::get()),
-        }
+            pool: Arc::new(Pool::new(num_cpus::get())),
+            unique_fields: T::get_unique_fields()
+                .into_iter()
+                .map(|field| {
+                    let field_int = get_unique_int_str(&field);
+                    (field, field_int)
+                })
+                .collect(),
+            versions: RwLock::new((0..SHARDS).map(|_| Arc::new(Tree::new::<T>())).collect()),
+            writers: (0..SHARDS).map(|_| Mutex::new(())).collect(),
+            unique: Mutex::new(()),
+            template: Tree::new::<T>(),
+            watchers: Mutex::new(Vec::new()),
+            workers: Mutex::new(workers),
+            checkpointer: Mutex::new(checkpointer),
+        })
     }
 
-    pub fn insert<T: Structure>(&mut self, query: &mut Query<T>) -> Result<Id, Error> {
-        self.check_query_field(query)?;
-
-        let wrapper = ItemWrapper::new(query.item.take().unwrap());
-        let id = wrapper.id.clone();
-        let encoded = encoder::encode_wrapper(&to_value(wrapper), &self.field_int_map);
-        self.insert_encoded_value(id.clone(), encoded)?;
+    pub fn insert<T: Structure>(&self, query: &mut Query<T>) -> Result<Id, Error> {
+        let prepared = self.prepare_insert(query)?;
+        let id = prepared.id.clone();
+        let _locks = self.lock(Some(get_shard(&id)));
+        let rc_item = self.insert_prepared(prepared)?;
+        self.notify(Event::Inserted(rc_item));
         Ok(id)
     }
 
-    pub fn find<T: Structure>(&self, query: &mut Query<T>) -> Result<Vec<T>, Error> {
+    /// Events of items matching the query are sent to the receiver until it is dropped.
+    pub fn watch<T: Structure>(&self, query: &mut Query<T>) -> Result<Receiver<Event<T>>, Error> {
         self.check_query_field(query)?;
 
-        Ok(self.search(&query, &QueryType::Find)?
-            .1
-            .into_iter()
-            .map(|wrapper| wrapper.item)
-            .collect::<Vec<_>>())
+        let mut task = Task::new(query, &QueryType::Find);
+        task.collate(&self.template.collations);
+
+        let (sender, receiver) = mpsc::channel();
+        self.watchers.lock().unwrap().push(Watcher::new(query.id.clone(),
+                                                        task.subs,
+                                                        sender,
+                                                        self.int_field_map.clone()));
+        Ok(receiver)
+    }
+
+    /// Found items are decoded from the snapshot, so no lock is held while decoding.
+    pub fn find<T: Structure>(&self, query: &mut Query<T>) -> Result<Snapshot, Error> {
+        self.check_query_field(query)?;
+        self.snapshot(query, &QueryType::Find)
     }
 
     pub fn count<T: Structure>(&self, query: &mut Query<T>) -> Result<usize, Error> {
@@ -67,173 +136,442 @@ impl Memory {
         Ok(self.search(&query, &QueryType::Count)?.0)
     }
 
-    pub fn find_one<T: Structure>(&self, query: &mut Query<T>) -> Result<Option<T>, Error> {
-        self.check_query_field(query)?;
-
+    pub fn find_one<T: Structure>(&self, query: &mut Query<T>) -> Result<Snapshot, Error> {
         query.limit = Some(1);
-        let mut items = self.find(query)?;
-
-        if !items.is_empty() {
-            Ok(Some(items.remove(0)))
-        } else if T::is_one() {
-            Ok(Some(T::new_empty()))
-        } else {
-            Ok(None)
-        }
+        self.find(query)
     }
 
-    pub fn update<T: Structure>(&mut self, query: &mut Query<T>) -> Result<usize, Error> {
+    pub fn update<T: Structure>(&self, query: &mut Query<T>) -> Result<usize, Error> {
         self.check_query_field(query)?;
 
         let updater = query.updater.take().unwrap();
-        let mut wrappers = self.delete_to_wrapper(query)?;
+        let _locks = self.lock(query.id.as_ref().map(|id| get_shard(id)));
+        let mut wrappers = self.search::<T>(&query, &QueryType::Find)?.1;
         let count = wrappers.len();
 
         for wrapper in &mut wrappers {
             updater(&mut wrapper.item);
         }
 
-        for wrapper in &wrappers {
-            let encoded = encoder::encode_wrapper(&to_value(wrapper), &self.field_int_map);
-            self.tree.insert(wrapper.id.clone(), encoded);
-        }
+        let encoded_values = wrappers.iter()
+            .map(|wrapper| {
+                (wrapper.id.clone(),
+                 encoder::encode_wrapper(&to_value(wrapper), &self.field_int_map))
+            })
+            .collect::<Vec<_>>();
+
+        self.check_unique(&encoded_values)?;
 
         if store::is_persistence() {
-            for wrapper in wrappers {
-                self.service.insert(wrapper.id.clone(), to_value(&wrapper))?;
+            let mut service = self.service.lock().unwrap();
+
+            for &(ref id, ref encoded) in &encoded_values {
+                service.insert(id.clone(), encoded.clone())?;
             }
         }
 
+        let shards = get_shards(encoded_values.iter().map(|&(ref id, _)| id));
+        let changes = self.apply(&shards, |shard, tree| {
+            encoded_values.iter()
+                .filter(|&&(ref id, _)| get_shard(id) == shard)
+                .filter_map(|&(ref id, ref encoded)| {
+                    let before = tree.id_map.get(id).cloned();
+                    tree.delete(id);
+                    tree.insert(id.clone(), encoded.clone());
+                    let after = tree.id_map.get(id).cloned();
+                    before.and_then(|before| after.map(|after| (before, after)))
+                })
+                .collect::<Vec<_>>()
+        });
+
+        for (before, after) in changes.into_iter().flat_map(Vec::into_iter) {
+            self.notify(Event::Updated {
+                before: before,
+                after: after,
+            });
+        }
+
         Ok(count)
     }
 
-    pub fn replace<T: Structure>(&mut self, query: &mut Query<T>) -> Result<(), Error> {
+    pub fn replace<T: Structure>(&self, query: &mut Query<T>) -> Result<(), Error> {
         self.check_query_field(query)?;
 
         if query.id.is_some() {
             query.item.as_mut().unwrap().set_id(query.id.clone().unwrap());
         }
 
-        let wrapper = ItemWrapper::new(query.item.clone().unwrap());
-        self.delete_to_wrapper::<T>(&mut Query::new().id(wrapper.id.clone()))?;
-        self.insert(query)?;
+        let prepared = self.prepare_insert(query)?;
+        let shard = get_shard(&prepared.id);
+        let _locks = self.lock(Some(shard));
+
+        if !self.is_one {
+            self.check_unique(&[(prepared.id.clone(), prepared.encoded.clone())])?;
+        }
+
+        // The insert record replaces the old item when the log is replayed, so the item is
+        // logged once and replaced in one change.
+        let before = self.versions()[shard].id_map.get(&prepared.id).cloned();
+        let after = self.insert_prepared(prepared)?;
+
+        self.notify(match before {
+            Some(before) => {
+                Event::Updated {
+                    before: before,
+                    after: after,
+                }
+            }
+            None => Event::Inserted(after),
+        });
 
         Ok(())
     }
 
-    pub fn delete<T: Structure>(&mut self, query: &mut Query<T>) -> Result<Vec<T>, Error> {
+    pub fn delete<T: Structure>(&self, query: &mut Query<T>) -> Result<Vec<T>, Error> {
         self.check_query_field(query)?;
 
-        Ok(self.delete_to_wrapper(query)?
-            .into_iter()
+        let _locks = self.lock(query.id.as_ref().map(|id| get_shard(id)));
+        let wrappers = self.search::<T>(&query, &QueryType::Find)?.1;
+        let ids = wrappers.iter().map(|wrapper| wrapper.id.clone()).collect::<Vec<_>>();
+        self.delete_by_ids(&ids)?;
+
+        Ok(wrappers.into_iter()
             .map(|wrapper| wrapper.item)
             .collect::<Vec<_>>())
     }
 
-    pub fn insert_encoded_value(&mut self, id: Id, value: Value) -> Result<(), Error> {
-        if self.is_one {
-            self.clear()?;
-            self.tree.insert(id.clone(), value.clone());
+    /// Remove expired items one shard at a time, they are logged as deleted.
+    pub fn sweep(&self) -> Result<usize, Error> {
+        let now = ttl::now();
+        let mut count = 0;
 
-            if store::is_persistence() {
-                self.service.clear()?;
-                self.service.insert(id.clone(), value.clone())?;
-            }
+        for shard in 0..SHARDS {
+            let _locks = self.lock(Some(shard));
+            let ids = self.versions()[shard].get_expired_ids(now);
+            self.delete_by_ids(&ids)?;
+            count += ids.len();
+        }
+
+        Ok(count)
+    }
+
+    /// Insert values loaded from the data file as one change, so readers see a single new
+    /// version of each shard. They are on disk already, so they are not logged.
+    pub fn insert_encoded_values(&self, values: Vec<(Id, Value)>) -> Result<(), Error> {
+        let _locks = self.lock(None);
+        let shards = (0..SHARDS).collect::<Vec<_>>();
+        let clear = self.is_one && !values.is_empty();
+
+        let values = if clear {
+            values.into_iter().last().into_iter().collect()
         } else {
-            self.tree.insert(id.clone(), value.clone());
+            values
+        };
+
+        let rc_items = values.into_iter()
+            .map(|(id, value)| (get_shard(&id), self.template.create_item(id, value)))
+            .collect::<Vec<_>>();
 
-            if store::is_persistence() {
-                self.service.insert(id.clone(), value.clone())?;
+        self.apply(&shards, |shard, tree| {
+            if clear {
+                tree.clear();
             }
+
+            for &(item_shard, ref rc_item) in &rc_items {
+                if item_shard == shard {
+                    tree.insert_item(rc_item.clone());
+                }
+            }
+        });
+
+        for (_, rc_item) in rc_items {
+            self.notify(Event::Inserted(rc_item));
         }
 
         Ok(())
     }
 
-    fn clear(&mut self) -> Result<(), Error> {
-        self.tree.clear();
+    /// Encode the item and create its tree item, no lock is needed.
+    fn prepare_insert<T: Structure>(&self, query: &mut Query<T>) -> Result<Prepared, Error> {
+        self.check_query_field(query)?;
+
+        let wrapper = ItemWrapper::new(query.item.take().unwrap());
+        let id = wrapper.id.clone();
+        let encoded = encoder::encode_wrapper(&to_value(wrapper), &self.field_int_map);
+        let rc_item = self.template.create_item(id.clone(), encoded.clone());
+        let line = if store::is_persistence() {
+            Some(create_insert_line(id.clone(), encoded.clone()))
+        } else {
+            None
+        };
+
+        Ok(Prepared {
+            id: id,
+            encoded: encoded,
+            rc_item: rc_item,
+            line: line,
+        })
+    }
+
+    /// The caller holds the lock of the shard of the item, or of every shard if the struct
+    /// is one, which is cleared first. An item with an existing id replaces it. Changes are
+    /// logged before applied, so a failed write leaves the memory as it was.
+    fn insert_prepared(&self, prepared: Prepared) -> Result<RcItem, Error> {
+        let Prepared { id, encoded, rc_item, line } = prepared;
+        let shard = get_shard(&id);
+
+        if !self.is_one {
+            self.check_unique(&[(id.clone(), encoded.clone())])?;
+        }
 
         if store::is_persistence() {
-            self.service.clear()?;
+            let mut service = self.service.lock().unwrap();
+
+            if self.is_one {
+                service.clear()?;
+            }
+
+            match line {
+                Some(line) => service.insert_line(&line)?,
+                None => service.insert(id, encoded)?,
+            }
         }
 
-        Ok(())
+        let shards = if self.is_one {
+            (0..SHARDS).collect::<Vec<_>>()
+        } else {
+            vec![shard]
+        };
+
+        self.apply(&shards, |item_shard, tree| {
+            if self.is_one {
+                tree.clear();
+            }
+
+            if item_shard == shard {
+                tree.insert_item(rc_item.clone());
+            }
+        });
+
+        Ok(rc_item)
     }
 
-    fn delete_to_wrapper<T: Structure>(&mut self,
-                                       query: &mut Query<T>)
-                                       -> Result<Vec<ItemWrapper<T>>, Error> {
-        let wrappers = self.search::<T>(&query, &QueryType::Find)?.1;
+    /// Delete the items, then notify the watchers of their removal.
+    fn delete_by_ids(&self, ids: &[Id]) -> Result<(), Error> {
+        let mut logged = Vec::new();
+        let mut result = Ok(());
 
-        for wrapper in &wrappers {
-            self.tree.delete(&wrapper.id);
+        if store::is_persistence() {
+            let mut service = self.service.lock().unwrap();
+
+            for id in ids {
+                if let Err(err) = service.delete(id.clone()) {
+                    result = Err(err);
+                    break;
+                }
 
-            if store::is_persistence() {
-                self.service.delete(wrapper.id.clone())?;
+                logged.push(id);
             }
+        } else {
+            logged.extend(ids);
         }
 
-        Ok(wrappers)
+        let shards = get_shards(logged.iter().cloned());
+        let removed = self.apply(&shards, |shard, tree| {
+            logged.iter()
+                .filter(|id| get_shard(id) == shard)
+                .filter_map(|id| {
+                    let rc_item = tree.id_map.get(*id).cloned();
+                    tree.delete(id);
+                    rc_item
+                })
+                .collect::<Vec<_>>()
+        });
+
+        let removed = removed.into_iter().flat_map(Vec::into_iter).collect::<Vec<_>>();
+        let left = self.versions().iter().map(|tree| tree.id_map.len()).sum::<usize>();
+        let threshold = store::get_compaction_threshold();
+
+        // Removing more than the compaction ratio of the items folds the log at once, so the
+        // data file is compacted without waiting for the checkpoint interval.
+        if removed.len() as f64 > (removed.len() + left) as f64 * threshold {
+            if let Some(ref checkpointer) = *self.checkpointer.lock().unwrap() {
+                let _ = checkpointer.send(());
+            }
+        }
+
+        for rc_item in removed {
+            self.notify(Event::Removed(rc_item));
+        }
+
+        result
     }
 
-    fn search<T: Structure>(&self,
-                            query: &&mut Query<T>,
-                            query_type: &QueryType)
-                            -> Result<(usize, Vec<ItemWrapper<T>>), Error> {
-        let (count, values) = self.tree.search(&self.pool, query, query_type)?;
-        let wrappers = if query_type == &QueryType::Find && count > 0 {
-            let wrapper_map = Mutex::new(VecMap::new());
-            self.pool.scoped(|scope| {
-                let wrapper_map = &wrapper_map;
-                let int_field_map = &self.int_field_map;
-
-                for (i, value) in values.into_iter().enumerate() {
-                    let ptr = AtomicPtr::new(value as *mut Value);
-
-                    scope.execute(move || {
-                        let wrapper = unsafe { &*ptr.load(Ordering::SeqCst) }
-                            .to_wrapper::<T>(&*int_field_map)
-                            .unwrap();
-                        wrapper_map.lock().unwrap().insert(i, wrapper);
-                    })
-                }
-            });
+    #[inline]
+    fn notify(&self, event: Event<RcItem>) {
+        self.watchers.lock().unwrap().retain(|watcher| watcher.notify(&event));
+    }
+
+    /// Lock the shard, or every shard without one. Structs with unique fields are locked as a
+    /// whole first, as their values are checked against every shard.
+    fn lock<'a>(&'a self, shard: Option<usize>) -> Vec<MutexGuard<'a, ()>> {
+        let mut locks = Vec::new();
+
+        if !self.unique_fields.is_empty() {
+            locks.push(self.unique.lock().unwrap());
+        }
+
+        match shard {
+            Some(shard) if !self.is_one => locks.push(self.writers[shard].lock().unwrap()),
+            _ => locks.extend(self.writers.iter().map(|writer| writer.lock().unwrap())),
+        }
+
+        locks
+    }
+
+    /// Versions of the shards to read, changes after the call are not seen by them.
+    #[inline]
+    fn versions(&self) -> Vec<Arc<Tree>> {
+        self.versions.read().unwrap().clone()
+    }
+
+    /// Change the trees of the shards as one version, the caller holds their locks. Changes
+    /// copy the paths they change, so readers keep searching the versions they have.
+    fn apply<R, F>(&self, shards: &[usize], mut change: F) -> Vec<R>
+        where F: FnMut(usize, &mut Tree) -> R
+    {
+        let mut trees = {
+            let versions = self.versions.read().unwrap();
+            shards.iter().map(|&shard| (*versions[shard]).clone()).collect::<Vec<_>>()
+        };
+
+        let results = shards.iter()
+            .zip(&mut trees)
+            .map(|(&shard, tree)| change(shard, tree))
+            .collect();
 
-            let mut wrappers = Vec::new();
-            let mut wrapper_map = wrapper_map.into_inner().unwrap();
+        let mut versions = self.versions.write().unwrap();
 
-            for i in 0..count {
-                wrappers.push(wrapper_map.remove(i).unwrap());
+        for (&shard, tree) in shards.iter().zip(trees) {
+            versions[shard] = Arc::new(tree);
+        }
+
+        results
+    }
+
+    /// Check encoded values against the unique fields, ids of `values` are ignored in the tree.
+    fn check_unique(&self, values: &[(Id, Value)]) -> Result<(), Error> {
+        if self.unique_fields.is_empty() {
+            return Ok(());
+        }
+
+        let versions = self.versions();
+        let ids = values.iter().map(|&(ref id, _)| id).collect::<HashSet<_>>();
+
+        for &(ref field, ref field_int) in &self.unique_fields {
+            let mut checked: Vec<Value> = Vec::new();
+
+            for &(_, ref value) in values {
+                let field_value = match value.get("item").and_then(|item| item.get(field_int)) {
+                    Some(field_value) if field_value.is_number() || field_value.is_string() => {
+                        field_value
+                    }
+                    _ => continue,
+                };
+
+                let collated = match self.template.collations.get(field_int) {
+                    Some(collation) => collation.collate(field_value),
+                    None => field_value.clone(),
+                };
+
+                let duplicated = checked.iter().any(|other| Math::eq(other, &collated)) ||
+                                 versions.iter().any(|tree| {
+                    tree.get_group(field_int, &collated)
+                        .map_or(false,
+                                |group| group.keys().any(|id| !ids.contains(id)))
+                });
+
+                if duplicated {
+                    return Err(Error::DuplicateKey {
+                        field: field.clone(),
+                        value: field_value.clone(),
+                    });
+                }
+
+                checked.push(collated);
             }
+        }
 
-            wrappers
+        Ok(())
+    }
 
-        } else {
-            Vec::new()
+    fn search<T: Structure>(&self,
+                            query: &&mut Query<T>,
+                            query_type: &QueryType)
+                            -> Result<(usize, Vec<ItemWrapper<T>>), Error> {
+        let snapshot = self.snapshot(query, query_type)?;
+        Ok((snapshot.count, snapshot.decode()?))
+    }
+
+    #[inline]
+    fn snapshot<T: Structure>(&self,
+                              query: &Query<T>,
+                              query_type: &QueryType)
+                              -> Result<Snapshot, Error> {
+        let versions = self.versions();
+        let trees = match query.id {
+            Some(ref id) => vec![&*versions[get_shard(id)]],
+            None => versions.iter().map(|version| &**version).collect(),
         };
 
-        Ok((count, wrappers))
+        let (count, values) = tree::search(&trees, query, query_type)?;
+        Ok(Snapshot::new(count, values, self.int_field_map.clone(), self.pool.clone()))
     }
 
     #[inline]
     fn check_query_field<T>(&self, query: &Query<T>) -> Result<(), Error> {
-        for field in &query.conditions {
-            if !self.int_field_map.contains_key(field.0) {
-                let mut found = false;
-
-                for field_int in self.int_field_map.keys() {
-                    if get_len_field_int(field_int) == *field.0 {
-                        found = true;
-                        break;
-                    }
-                }
+        let fields = query.conditions.keys().chain(query.orders.iter().map(|order| &order.0));
 
-                if !found {
-                    return Err(Error::FieldNotFound);
-                }
+        for field_int in fields {
+            if !self.has_field(field_int) {
+                return Err(Error::FieldNotFound);
             }
         }
 
+        if query.text.is_some() && self.template.texts.is_empty() {
+            return Err(Error::FieldNotFound);
+        }
+
         Ok(())
     }
+
+    #[inline]
+    fn has_field(&self, field_int: &str) -> bool {
+        self.int_field_map.contains_key(field_int) ||
+        self.int_field_map.keys().any(|other| get_len_field_int(other) == field_int)
+    }
+}
+
+impl Drop for Memory {
+    /// A dropped or replaced memory stops its background workers.
+    fn drop(&mut self) {
+        self.workers.lock().unwrap().clear();
+        self.checkpointer.lock().unwrap().take();
+    }
+}
+
+/// Shard of the item, by the FNV-1a hash of its id.
+fn get_shard(id: &str) -> usize {
+    let hash = id.bytes()
+        .fold(2_166_136_261u32,
+              |hash, byte| (hash ^ byte as u32).wrapping_mul(16_777_619));
+    hash as usize % SHARDS
+}
+
+/// Shards of the ids, sorted and without duplicates.
+fn get_shards<'a, I: Iterator<Item = &'a Id>>(ids: I) -> Vec<usize> {
+    let mut shards = ids.map(|id| get_shard(id)).collect::<Vec<_>>();
+    shards.sort();
+    shards.dedup();
+    shards
 }
@@ -0,0 +1,66 @@
+use std::sync::{Arc, Mutex};
+use std::fmt;
+use serde_json::Value;
+use traits::{Structure, FieldIntMap};
+use item::ItemWrapper;
+use item::convertor::Convertor;
+use error::Error;
+use scoped_pool::Pool;
+use vec_map::VecMap;
+
+
+/// Item versions found by a query, they stay readable after the memory lock is released.
+pub struct Snapshot {
+    pub count: usize,
+    values: Vec<Arc<Value>>,
+    int_field_map: Arc<FieldIntMap>,
+    pool: Arc<Pool>,
+}
+
+impl Snapshot {
+    pub fn new(count: usize,
+               values: Vec<Arc<Value>>,
+               int_field_map: Arc<FieldIntMap>,
+               pool: Arc<Pool>)
+               -> Snapshot {
+        Snapshot {
+            count: count,
+            values: values,
+            int_field_map: int_field_map,
+            pool: pool,
+        }
+    }
+
+    pub fn decode<T: Structure>(self) -> Result<Vec<ItemWrapper<T>>, Error> {
+        let Snapshot { values, int_field_map, pool, .. } = self;
+        let wrapper_map = Mutex::new(VecMap::new());
+        let count = values.len();
+
+        pool.scoped(|scope| {
+            let wrapper_map = &wrapper_map;
+            let int_field_map = &int_field_map;
+
+            for (i, value) in values.into_iter().enumerate() {
+                scope.execute(move || {
+                    let wrapper = value.to_wrapper::<T>(&*int_field_map);
+                    wrapper_map.lock().unwrap().insert(i, wrapper);
+                })
+            }
+        });
+
+        let mut wrappers = Vec::new();
+        let mut wrapper_map = wrapper_map.into_inner().unwrap();
+
+        for i in 0..count {
+            wrappers.push(wrapper_map.remove(i).unwrap()?);
+        }
+
+        Ok(wrappers)
+    }
+}
+
+impl fmt::Debug for Snapshot {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Snapshot {{ count: {}, values: {} }}", self.count, self.values.len())
+    }
+}
@@ -0,0 +1,26 @@
+use std::thread;
+use std::sync::mpsc::{self, Sender, RecvTimeoutError};
+use store::{get_memory, get_sweep_interval};
+
+
+/// Remove expired items of the struct in the background, until the returned sender is dropped
+/// with its memory.
+pub fn spawn(struct_name: String) -> Sender<()> {
+    let (sender, receiver) = mpsc::channel::<()>();
+
+    thread::Builder::new()
+        .name(format!("sweep {}", struct_name))
+        .spawn(move || {
+            while let Err(RecvTimeoutError::Timeout) = receiver.recv_timeout(get_sweep_interval()) {
+                if let Some(memory) = get_memory(&struct_name) {
+                    match memory.sweep() {
+                        Ok(count) => thread_trace!("sweep {}, removed: {}", struct_name, count),
+                        Err(err) => error!("sweep {} failed: {}", struct_name, err),
+                    }
+                }
+            }
+        })
+        .unwrap();
+
+    sender
+}
@@ -0,0 +1,70 @@
+use std::cmp;
+use std::thread;
+use std::sync::mpsc::{self, Sender, RecvTimeoutError};
+use std::time::{Duration, Instant};
+use loader;
+use compactor;
+use store::{get_memory, persistences, get_checkpoint_interval, get_checkpoint_size};
+
+
+/// Fold the log into the data file in the background, once it is large or old enough or a
+/// fold is sent, then compact the data file if needed. Runs until the returned sender is
+/// dropped with its memory.
+pub fn spawn(struct_name: String) -> Sender<()> {
+    let (sender, receiver) = mpsc::channel::<()>();
+
+    thread::Builder::new()
+        .name(format!("checkpoint {}", struct_name))
+        .spawn(move || {
+            let mut last = Instant::now();
+
+            loop {
+                let sent = match receiver.recv_timeout(get_tick()) {
+                    Ok(()) => true,
+                    Err(RecvTimeoutError::Timeout) => false,
+                    Err(RecvTimeoutError::Disconnected) => break,
+                };
+
+                let size = loader::get_log_size(&struct_name).unwrap_or(0);
+                if size == 0 ||
+                   (!sent && size < get_checkpoint_size() &&
+                    last.elapsed() < get_checkpoint_interval()) {
+                    continue;
+                }
+
+                checkpoint(&struct_name);
+                last = Instant::now();
+
+                if let Err(err) = compactor::compact_if_needed(&struct_name) {
+                    error!("compact {} failed: {}", struct_name, err);
+                }
+            }
+        })
+        .unwrap();
+
+    sender
+}
+
+/// Writers of the struct wait on the persistence service while the log is folded, queries
+/// and other structs keep running.
+pub fn checkpoint(struct_name: &str) {
+    let persistence_store = persistences().read().unwrap();
+    let _persistence = match persistence_store.get(struct_name) {
+        Some(persistence) => persistence.write().unwrap(),
+        None => return,
+    };
+
+    if let Some(memory) = get_memory(struct_name) {
+        let _service = memory.service.lock().unwrap();
+
+        match loader::checkpoint(struct_name) {
+            Ok(()) => thread_trace!("checkpoint {}", struct_name),
+            Err(err) => error!("checkpoint {} failed: {}", struct_name, err),
+        }
+    }
+}
+
+#[inline]
+fn get_tick() -> Duration {
+    cmp::min(get_checkpoint_interval(), Duration::from_secs(1))
+}
@@ -0,0 +1,404 @@
+use std::collections::HashMap;
+use std::str;
+use serde_json::{self, Value, Number, Map};
+use encoder::{bin_encode, bin_decode};
+use error::Error;
+use super::Persistence;
+use super::meta::SimpleMeta;
+use utils::lz;
+use BINENCODE;
+
+
+/// Records of version 0 are the JSON string of the value, bincoded when `BINENCODE`.
+pub const RECORD_VERSION: u32 = 1;
+/// Version of the headers and index layout, files of other versions are refused.
+pub const FORMAT_VERSION: u64 = 2;
+/// Start the headers of the data, index and log files, no bincoded file has a length prefix
+/// this large and no log a line starting this way.
+pub const DATA_MAGIC: &'static [u8] = b"\x89ARD\r\n\x1a\n";
+pub const INDEX_MAGIC: &'static [u8] = b"\x89ARX\r\n\x1a\n";
+pub const LOG_MAGIC: &'static [u8] = b"\x89ARL\r\n\x1a\n";
+
+const NULL: u8 = 0;
+const FALSE: u8 = 1;
+const TRUE: u8 = 2;
+const UNSIGNED: u8 = 3;
+const NEGATIVE: u8 = 4;
+const FLOAT: u8 = 5;
+const STRING: u8 = 6;
+const ARRAY: u8 = 7;
+const OBJECT: u8 = 8;
+
+
+pub fn encode_record(value: &Value, version: u32) -> Vec<u8> {
+    if version == 0 {
+        let data = value.to_string();
+
+        if BINENCODE {
+            bin_encode(data)
+        } else {
+            data.into_bytes()
+        }
+    } else {
+        let mut bytes = Vec::new();
+        write_value(&mut bytes, value);
+        bytes
+    }
+}
+
+/// Record bytes, compressed when asked and smaller, with whether they are.
+pub fn pack_record(value: &Value, version: u32, compress: bool) -> (Vec<u8>, bool) {
+    let bytes = encode_record(value, version);
+
+    if compress {
+        let compressed = lz::compress(&bytes);
+        if compressed.len() < bytes.len() {
+            return (compressed, true);
+        }
+    }
+
+    (bytes, false)
+}
+
+pub fn unpack_record(bytes: &[u8], version: u32, compressed: bool) -> Result<Value, Error> {
+    if compressed {
+        decode_record(&lz::decompress(bytes)?, version)
+    } else {
+        decode_record(bytes, version)
+    }
+}
+
+pub fn decode_record(bytes: &[u8], version: u32) -> Result<Value, Error> {
+    match version {
+        0 => {
+            let data = if BINENCODE {
+                bin_decode(bytes)?
+            } else {
+                str::from_utf8(bytes).map_err(|err| Error::Corrupt(err.to_string()))?.to_owned()
+            };
+
+            serde_json::from_str(&data)
+                .map_err(|err| Error::Corrupt(format!("invalid record: {}", err)))
+        }
+        RECORD_VERSION => {
+            let mut reader = Reader::new(bytes);
+            let value = reader.read_value()?;
+            reader.finish()?;
+            Ok(value)
+        }
+        _ => Err(Error::Corrupt(format!("unknown record version {}", version))),
+    }
+}
+
+/// The magic, format version and struct name a file starts with.
+pub fn encode_header(magic: &[u8], struct_name: &str) -> Vec<u8> {
+    let mut bytes = magic.to_vec();
+    write_varint(&mut bytes, FORMAT_VERSION);
+    write_str(&mut bytes, struct_name);
+    bytes
+}
+
+/// Length of the header the bytes start with, `None` for files written before headers.
+pub fn decode_header(bytes: &[u8],
+                     magic: &[u8],
+                     struct_name: &str)
+                     -> Result<Option<usize>, Error> {
+    if !bytes.starts_with(magic) {
+        return Ok(None);
+    }
+
+    let mut reader = Reader::new(&bytes[magic.len()..]);
+    let version = reader.read_varint()?;
+    if version != FORMAT_VERSION {
+        return Err(Error::UnsupportedFormat {
+            found: version,
+            expected: FORMAT_VERSION,
+        });
+    }
+
+    let name = reader.read_string()?;
+    if name != struct_name {
+        return Err(Error::Corrupt(format!("file of {} found for {}", name, struct_name)));
+    }
+
+    Ok(Some(magic.len() + reader.position))
+}
+
+pub fn encode_index(persistence: &Persistence) -> Vec<u8> {
+    let mut bytes = encode_header(INDEX_MAGIC, &persistence.struct_name);
+    write_varint(&mut bytes, persistence.version as u64);
+    write_varint(&mut bytes, persistence.size as u64);
+
+    write_varint(&mut bytes, persistence.field_int_map.len() as u64);
+    for (field, field_int) in &persistence.field_int_map {
+        write_str(&mut bytes, field);
+        write_str(&mut bytes, field_int);
+    }
+
+    write_varint(&mut bytes, persistence.metas.len() as u64);
+    for (id, meta) in &persistence.metas {
+        write_str(&mut bytes, id);
+        write_meta(&mut bytes, meta);
+    }
+
+    write_varint(&mut bytes, persistence.deleted.len() as u64);
+    for meta in &persistence.deleted {
+        write_meta(&mut bytes, meta);
+    }
+
+    bytes
+}
+
+/// Indexes without a header are the JSON of the persistence.
+pub fn decode_index(bytes: &[u8], struct_name: &str) -> Result<Persistence, Error> {
+    let header = decode_header(bytes, INDEX_MAGIC, struct_name)?;
+    if header.is_none() {
+        let data = if BINENCODE {
+            bin_decode(bytes)?
+        } else {
+            str::from_utf8(bytes).map_err(|err| Error::Corrupt(err.to_string()))?.to_owned()
+        };
+
+        return serde_json::from_str(&data)
+            .map_err(|err| Error::Corrupt(format!("invalid persistence index: {}", err)));
+    }
+
+    let mut reader = Reader::new(&bytes[header.unwrap()..]);
+    let mut persistence = Persistence::new(struct_name.to_owned(), HashMap::new());
+    persistence.version = reader.read_varint()? as u32;
+    persistence.size = reader.read_varint()? as usize;
+
+    for _ in 0..reader.read_varint()? {
+        let field = reader.read_string()?;
+        persistence.field_int_map.insert(field, reader.read_string()?);
+    }
+
+    for _ in 0..reader.read_varint()? {
+        let id = reader.read_string()?;
+        persistence.metas.insert(id, reader.read_meta()?);
+    }
+
+    for _ in 0..reader.read_varint()? {
+        persistence.deleted.push(reader.read_meta()?);
+    }
+
+    reader.finish()?;
+    Ok(persistence)
+}
+
+fn write_value(bytes: &mut Vec<u8>, value: &Value) {
+    match *value {
+        Value::Null => bytes.push(NULL),
+        Value::Bool(false) => bytes.push(FALSE),
+        Value::Bool(true) => bytes.push(TRUE),
+        Value::Number(ref number) => {
+            if let Some(n) = number.as_u64() {
+                bytes.push(UNSIGNED);
+                write_varint(bytes, n);
+            } else if let Some(n) = number.as_i64() {
+                bytes.push(NEGATIVE);
+                write_varint(bytes, !(n as u64));
+            } else {
+                bytes.push(FLOAT);
+                let bits = number.as_f64().unwrap().to_bits();
+                bytes.extend((0..8).map(|index| (bits >> (index * 8)) as u8));
+            }
+        }
+        Value::String(ref string) => {
+            bytes.push(STRING);
+            write_str(bytes, string);
+        }
+        Value::Array(ref values) => {
+            bytes.push(ARRAY);
+            write_varint(bytes, values.len() as u64);
+
+            for value in values {
+                write_value(bytes, value);
+            }
+        }
+        Value::Object(ref map) => {
+            bytes.push(OBJECT);
+            write_varint(bytes, map.len() as u64);
+
+            for (key, value) in map {
+                write_key(bytes, key);
+                write_value(bytes, value);
+            }
+        }
+    }
+}
+
+/// Field ints are stored as numbers, flagged by the lowest bit.
+fn write_key(bytes: &mut Vec<u8>, key: &str) {
+    match key.parse::<u64>() {
+        Ok(n) if n.to_string() == key && n >> 63 == 0 => write_varint(bytes, n << 1 | 1),
+        _ => {
+            write_varint(bytes, (key.len() as u64) << 1);
+            bytes.extend_from_slice(key.as_bytes());
+        }
+    }
+}
+
+fn write_meta(bytes: &mut Vec<u8>, meta: &SimpleMeta) {
+    write_varint(bytes, meta.offset() as u64);
+    write_varint(bytes, meta.real() as u64);
+    write_varint(bytes, meta.size() as u64);
+    write_varint(bytes, meta.checksum().map_or(0, |checksum| checksum as u64 + 1));
+    write_varint(bytes, meta.is_compressed() as u64);
+}
+
+fn write_str(bytes: &mut Vec<u8>, string: &str) {
+    write_varint(bytes, string.len() as u64);
+    bytes.extend_from_slice(string.as_bytes());
+}
+
+fn write_varint(bytes: &mut Vec<u8>, mut n: u64) {
+    while n >= 0x80 {
+        bytes.push(n as u8 | 0x80);
+        n >>= 7;
+    }
+
+    bytes.push(n as u8);
+}
+
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8]) -> Reader<'a> {
+        Reader {
+            bytes: bytes,
+            position: 0,
+        }
+    }
+
+    fn finish(&self) -> Result<(), Error> {
+        if self.position == self.bytes.len() {
+            Ok(())
+        } else {
+            Err(Error::Corrupt("trailing bytes".to_owned()))
+        }
+    }
+
+    fn read_bytes(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        if len > self.bytes.len() - self.position {
+            return Err(Error::Corrupt("unexpected end".to_owned()));
+        }
+
+        let bytes = &self.bytes[self.position..self.position + len];
+        self.position += len;
+        Ok(bytes)
+    }
+
+    fn read_byte(&mut self) -> Result<u8, Error> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    fn read_varint(&mut self) -> Result<u64, Error> {
+        let mut n = 0;
+
+        for shift in 0..10 {
+            let byte = self.read_byte()?;
+            n |= ((byte & 0x7f) as u64) << (shift * 7);
+
+            if byte & 0x80 == 0 {
+                return Ok(n);
+            }
+        }
+
+        Err(Error::Corrupt("invalid varint".to_owned()))
+    }
+
+    fn read_len(&mut self) -> Result<usize, Error> {
+        let len = self.read_varint()?;
+
+        if len > (self.bytes.len() - self.position) as u64 {
+            Err(Error::Corrupt("invalid length".to_owned()))
+        } else {
+            Ok(len as usize)
+        }
+    }
+
+    fn read_string(&mut self) -> Result<String, Error> {
+        let len = self.read_len()?;
+        let bytes = self.read_bytes(len)?;
+
+        str::from_utf8(bytes)
+            .map(|string| string.to_owned())
+            .map_err(|err| Error::Corrupt(err.to_string()))
+    }
+
+    fn read_key(&mut self) -> Result<String, Error> {
+        let n = self.read_varint()?;
+
+        if n & 1 == 1 {
+            Ok((n >> 1).to_string())
+        } else {
+            let bytes = self.read_bytes((n >> 1) as usize)?;
+
+            str::from_utf8(bytes)
+                .map(|string| string.to_owned())
+                .map_err(|err| Error::Corrupt(err.to_string()))
+        }
+    }
+
+    fn read_meta(&mut self) -> Result<SimpleMeta, Error> {
+        let offset = self.read_varint()? as usize;
+        let real = self.read_varint()? as usize;
+        let size = self.read_varint()? as usize;
+        let checksum = match self.read_varint()? {
+            0 => None,
+            n => Some((n - 1) as u32),
+        };
+
+        let compressed = self.read_varint()? & 1 == 1;
+
+        Ok(SimpleMeta::new(offset, real, size, checksum, compressed))
+    }
+
+    fn read_value(&mut self) -> Result<Value, Error> {
+        Ok(match self.read_byte()? {
+            NULL => Value::Null,
+            FALSE => Value::Bool(false),
+            TRUE => Value::Bool(true),
+            UNSIGNED => Value::Number(Number::from(self.read_varint()?)),
+            NEGATIVE => Value::Number(Number::from(!self.read_varint()? as i64)),
+            FLOAT => {
+                let bits = self.read_bytes(8)?
+                    .iter()
+                    .enumerate()
+                    .fold(0, |bits, (index, &byte)| bits | ((byte as u64) << (index * 8)));
+
+                Number::from_f64(f64::from_bits(bits))
+                    .map(Value::Number)
+                    .ok_or_else(|| Error::Corrupt("invalid float".to_owned()))?
+            }
+            STRING => Value::String(self.read_string()?),
+            ARRAY => {
+                let len = self.read_len()?;
+                let mut values = Vec::with_capacity(len);
+
+                for _ in 0..len {
+                    values.push(self.read_value()?);
+                }
+
+                Value::Array(values)
+            }
+            OBJECT => {
+                let len = self.read_len()?;
+                let mut map = Map::new();
+
+                for _ in 0..len {
+                    let key = self.read_key()?;
+                    map.insert(key, self.read_value()?);
+                }
+
+                Value::Object(map)
+            }
+            tag => return Err(Error::Corrupt(format!("unknown value tag {}", tag))),
+        })
+    }
+}
@@ -0,0 +1,175 @@
+use std::cell::RefCell;
+use std::fs::File;
+use std::io::ErrorKind;
+use std::sync::{Arc, Weak, Mutex, Condvar};
+use std::thread;
+use std::time::Duration;
+use config::Durability;
+use store::get_durability;
+use error::Error;
+
+
+thread_local! {
+    static PENDING: RefCell<Option<(Arc<Committer>, u64)>> = RefCell::new(None);
+}
+
+/// Syncs the log once per interval for every line written since the last sync.
+pub struct Committer {
+    file: File,
+    state: Mutex<State>,
+    synced: Condvar,
+}
+
+#[derive(Default)]
+struct State {
+    /// Lines written, each waits for the sync covering it.
+    written: u64,
+    /// Lines synced.
+    synced: u64,
+    /// Syncs done, one covers every line written since the one before.
+    syncs: u64,
+    /// Error of the last sync and the lines it covered.
+    failed: Option<(u64, ErrorKind, String)>,
+}
+
+impl Committer {
+    /// The interval is read from the config before each sync, `interval` is kept once the
+    /// durability is no longer group commit, for the lines still waiting.
+    pub fn spawn(file: File, interval: Duration) -> Arc<Committer> {
+        let committer = Arc::new(Committer {
+            file: file,
+            state: Mutex::new(State::default()),
+            synced: Condvar::new(),
+        });
+        let weak = Arc::downgrade(&committer);
+
+        thread::spawn(move || {
+            let mut interval = interval;
+
+            loop {
+                if let Durability::GroupCommit { interval: configured } = get_durability() {
+                    interval = configured;
+                }
+
+                thread::sleep(interval);
+
+                match Weak::upgrade(&weak) {
+                    Some(committer) => committer.sync(),
+                    None => break,
+                }
+            }
+        });
+
+        committer
+    }
+
+    /// Count a written line, the current thread waits for its sync in `wait()`.
+    pub fn written(committer: &Arc<Committer>) {
+        let ticket = {
+            let mut state = committer.state.lock().unwrap();
+            state.written += 1;
+            state.written
+        };
+
+        PENDING.with(|pending| *pending.borrow_mut() = Some((committer.clone(), ticket)));
+    }
+
+    fn sync(&self) {
+        let written = {
+            let state = self.state.lock().unwrap();
+            if state.written == state.synced {
+                return;
+            }
+
+            state.written
+        };
+
+        let result = self.file.sync_data();
+
+        {
+            let mut state = self.state.lock().unwrap();
+
+            match result {
+                Ok(()) => {
+                    state.synced = written;
+                    state.syncs += 1;
+                }
+                Err(err) => {
+                    error!("sync log failed: {}", err);
+                    state.failed = Some((written, err.kind(), err.to_string()));
+                }
+            }
+        }
+
+        self.synced.notify_all();
+    }
+
+    fn wait_for(&self, ticket: u64) -> Result<(), Error> {
+        let mut state = self.state.lock().unwrap();
+
+        while state.synced < ticket {
+            if let Some((written, kind, ref message)) = state.failed {
+                if ticket <= written {
+                    return Err(Error::Io(kind, message.clone()));
+                }
+            }
+
+            state = self.synced.wait(state).unwrap();
+        }
+
+        Ok(())
+    }
+}
+
+/// Block until the last line written by the current thread is synced, called without locks.
+pub fn wait() -> Result<(), Error> {
+    match PENDING.with(|pending| pending.borrow_mut().take()) {
+        Some((committer, ticket)) => committer.wait_for(ticket),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Barrier};
+    use std::thread;
+    use std::time::Duration;
+    use mktemp::Temp;
+    use utils::file::open_with_write;
+    use config::{self, Durability};
+    use super::{Committer, wait};
+
+    #[test]
+    fn test_group_commit() {
+        let temp = Temp::new_file().unwrap();
+        let file = open_with_write(temp.to_path_buf()).unwrap();
+        config::durability(Durability::GroupCommit { interval: Duration::from_millis(100) });
+
+        // The configured interval is used, not the one given.
+        let committer = Committer::spawn(file, Duration::from_secs(3600));
+        let barrier = Arc::new(Barrier::new(4));
+
+        let threads = (0..4)
+            .map(|_| {
+                let committer = committer.clone();
+                let barrier = barrier.clone();
+
+                thread::spawn(move || {
+                    barrier.wait();
+                    Committer::written(&committer);
+                    wait().unwrap();
+
+                    assert!(committer.state.lock().unwrap().syncs > 0);
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        let state = committer.state.lock().unwrap();
+        assert_eq!(state.synced, 4);
+        assert!(state.syncs < state.synced, "{} syncs", state.syncs);
+    }
+}
@@ -1,6 +1,13 @@
 
 use std::fs::File;
+use std::sync::Arc;
 use utils::file::open_log_file;
+use config::Durability;
+use store::get_durability;
+use super::committer::Committer;
+use super::codec::LOG_MAGIC;
+use loader::ensure_header;
+use error::Error;
 use serde_json::Value;
 use to_value;
 use item::Id;
@@ -43,25 +50,57 @@ impl Line {
 
 pub struct Logger {
     file: File,
+    committer: Option<Arc<Committer>>,
 }
 
 impl Logger {
-    pub fn new(struct_name: &str) -> Logger {
-        Logger { file: open_log_file(struct_name) }
+    pub fn new(struct_name: &str) -> Result<Logger, Error> {
+        let mut file = open_log_file(struct_name)?;
+        ensure_header(&mut file, LOG_MAGIC, struct_name)?;
+
+        Ok(Logger {
+            file: file,
+            committer: None,
+        })
+    }
+
+    pub fn insert(&mut self, id: Id, value: Value) -> Result<(), Error> {
+        self.write(&create_insert_line(id, value))
+    }
+
+    /// The line is written with a single call, so a failed write leaves at most a torn line.
+    pub fn write(&mut self, line: &str) -> Result<(), Error> {
+        self.file.write_all(format!("{}\n", line).as_bytes())?;
+        self.commit()
     }
 
-    pub fn insert(&mut self, id: Id, value: Value) {
-        writeln!(self.file,
-                 "{}",
-                 to_value(Line::new(Action::Insert).id(id).value(value)))
-            .unwrap();
+    pub fn delete(&mut self, id: Id) -> Result<(), Error> {
+        self.write(&to_value(Line::new(Action::Delete).id(id)).to_string())
     }
 
-    pub fn delete(&mut self, id: Id) {
-        writeln!(self.file, "{}", to_value(Line::new(Action::Delete).id(id))).unwrap();
+    pub fn clear(&mut self) -> Result<(), Error> {
+        self.write(&to_value(Line::new(Action::Clear)).to_string())
     }
 
-    pub fn clear(&mut self) {
-        writeln!(self.file, "{}", to_value(Line::new(Action::Clear))).unwrap();
+    fn commit(&mut self) -> Result<(), Error> {
+        match get_durability() {
+            Durability::None => (),
+            Durability::EveryWrite => self.file.sync_data()?,
+            Durability::GroupCommit { interval } => {
+                if self.committer.is_none() {
+                    let file = self.file.try_clone()?;
+                    self.committer = Some(Committer::spawn(file, interval));
+                }
+
+                Committer::written(self.committer.as_ref().unwrap());
+            }
+        }
+
+        Ok(())
     }
 }
+
+/// Lines can be created before the logger is locked.
+pub fn create_insert_line(id: Id, value: Value) -> String {
+    to_value(Line::new(Action::Insert).id(id).value(value)).to_string()
+}
@@ -1,30 +1,55 @@
+use serde::{Serialize, Serializer, Deserialize, Deserializer};
+use serde::de::Error;
+use serde::ser::SerializeTuple;
+use utils::crc32;
+
 
 pub struct Meta {
     pub offset: usize,
     pub real: usize,
     pub size: usize,
+    pub checksum: u32,
+    pub compressed: bool,
 }
 
 impl Meta {
-    pub fn new(bytes: &[u8]) -> Meta {
+    pub fn new(bytes: &[u8], compressed: bool) -> Meta {
         let size = bytes.len();
         Meta {
             offset: 0,
             real: size,
             size: size,
+            checksum: crc32::checksum(bytes),
+            compressed: compressed,
         }
     }
 
     pub fn to_simple(&self) -> SimpleMeta {
-        SimpleMeta(self.offset, self.real, self.size)
+        SimpleMeta(self.offset, self.real, self.size, Some(self.checksum), self.compressed)
     }
 }
 
 
-#[derive(Default, Debug, Clone, Serialize, Deserialize)]
-pub struct SimpleMeta(usize, usize, usize);
+/// Offset, used length, allocated length, checksum and compression of a record, indexes
+/// written before checksums have only the first three, and JSON indexes no compression.
+#[derive(Default, Debug, Clone)]
+pub struct SimpleMeta(usize, usize, usize, Option<u32>, bool);
 
 impl SimpleMeta {
+    pub fn new(offset: usize,
+               real: usize,
+               size: usize,
+               checksum: Option<u32>,
+               compressed: bool)
+               -> SimpleMeta {
+        SimpleMeta(offset, real, size, checksum, compressed)
+    }
+
+    /// The same record moved to `offset` without its unused space.
+    pub fn moved(&self, offset: usize) -> SimpleMeta {
+        SimpleMeta(offset, self.1, self.1, self.3, self.4)
+    }
+
     pub fn set_offset(&mut self, v: usize) {
         self.0 = v;
     }
@@ -33,6 +58,14 @@ impl SimpleMeta {
         self.1 = v;
     }
 
+    pub fn set_checksum(&mut self, v: Option<u32>) {
+        self.3 = v;
+    }
+
+    pub fn set_compressed(&mut self, v: bool) {
+        self.4 = v;
+    }
+
     pub fn offset(&self) -> usize {
         self.0
     }
@@ -44,4 +77,56 @@ impl SimpleMeta {
     pub fn size(&self) -> usize {
         self.2
     }
+
+    pub fn checksum(&self) -> Option<u32> {
+        self.3
+    }
+
+    pub fn is_compressed(&self) -> bool {
+        self.4
+    }
+
+    /// Records without a checksum are trusted.
+    pub fn verify(&self, bytes: &[u8]) -> bool {
+        self.3.map_or(true, |checksum| crc32::checksum(bytes) == checksum)
+    }
+}
+
+impl Serialize for SimpleMeta {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut tuple = serializer.serialize_tuple(if self.3.is_some() { 4 } else { 3 })?;
+        tuple.serialize_element(&self.0)?;
+        tuple.serialize_element(&self.1)?;
+        tuple.serialize_element(&self.2)?;
+
+        if let Some(checksum) = self.3 {
+            tuple.serialize_element(&checksum)?;
+        }
+
+        tuple.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for SimpleMeta {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<SimpleMeta, D::Error> {
+        let values = Vec::<u64>::deserialize(deserializer)?;
+
+        match values.len() {
+            3 => {
+                Ok(SimpleMeta(values[0] as usize,
+                              values[1] as usize,
+                              values[2] as usize,
+                              None,
+                              false))
+            }
+            4 => {
+                Ok(SimpleMeta(values[0] as usize,
+                              values[1] as usize,
+                              values[2] as usize,
+                              Some(values[3] as u32),
+                              false))
+            }
+            len => Err(D::Error::invalid_length(len, &"3 or 4 numbers")),
+        }
+    }
 }
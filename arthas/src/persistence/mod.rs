@@ -3,6 +3,9 @@ pub mod operation;
 pub mod meta;
 pub mod service;
 pub mod logger;
+pub mod checkpointer;
+pub mod committer;
+pub mod codec;
 
 
 use std::str;
@@ -10,12 +13,12 @@ use std::collections::HashMap;
 use item::StructName;
 use persistence::operation::Operation;
 use traits::FieldIntMap;
-use encoder::bin_encode;
 use item::Id;
 use self::meta::{Meta, SimpleMeta};
 use self::logger::{Line, Action};
 use serde_json;
-use BINENCODE;
+use error::Error;
+use store::is_compressed;
 
 
 #[derive(Default, Serialize, Deserialize, Debug, Clone)]
@@ -25,6 +28,9 @@ pub struct Persistence {
     pub metas: HashMap<Id, SimpleMeta>,
     pub deleted: Vec<SimpleMeta>,
     pub size: usize,
+    /// Encoding of the records, see `codec::RECORD_VERSION`.
+    #[serde(default)]
+    pub version: u32,
 }
 
 impl Persistence {
@@ -32,86 +38,91 @@ impl Persistence {
         Persistence {
             struct_name: struct_name,
             field_int_map: field_int_map,
+            version: codec::RECORD_VERSION,
             ..Default::default()
         }
     }
 
-    pub fn insert(&mut self, operation: Operation) -> (bool, SimpleMeta, Vec<u8>) {
-        let bytes = self.get_bytes(&operation).unwrap();
-        let meta = Meta::new(&bytes).to_simple();
-        let (append, meta) = self.insert_meta(operation.id.unwrap(), meta);
-        (append, meta, bytes)
+    pub fn insert(&mut self, operation: Operation) -> Result<(bool, SimpleMeta, Vec<u8>), Error> {
+        let id = operation.id.clone().ok_or(Error::RequiresId)?;
+        let (bytes, compressed) = self.get_bytes(&operation)
+            .ok_or_else(|| Error::Corrupt(format!("record {} has no value", id)))?;
+        let meta = Meta::new(&bytes, compressed).to_simple();
+        let (append, meta) = self.insert_meta(id, meta);
+        Ok((append, meta, bytes))
     }
 
-    pub fn delete(&mut self, operation: Operation) {
-        let removed = self.metas.remove(&operation.id.unwrap());
+    pub fn delete(&mut self, operation: Operation) -> Result<(), Error> {
+        let removed = self.metas.remove(&operation.id.ok_or(Error::RequiresId)?);
         if removed.is_some() {
             let meta = removed.unwrap();
             self.deleted.push(meta);
         }
+
+        Ok(())
     }
 
     pub fn clear(&mut self) {
         for (_, meta) in self.metas.drain() {
             self.deleted.push(meta);
         }
+    }
+
+    /// Ratio of the data file not used by live records.
+    pub fn get_dead_ratio(&self) -> f64 {
+        if self.size == 0 {
+            return 0.0;
+        }
 
-        self.size = 0;
+        let live = self.metas.values().map(|meta| meta.real()).sum::<usize>();
+        (self.size - live) as f64 / self.size as f64
     }
 
-    pub fn write_log(&mut self, log: String) -> Option<(bool, SimpleMeta, Vec<u8>)> {
-        let line = serde_json::from_str::<Line>(&log).unwrap();
-        match line.action {
-            Action::Insert => {
-                Some(self.insert(Operation::new().id(line.id.unwrap()).value(line.value.unwrap())))
+    pub fn write_log(&mut self,
+                     log: String)
+                     -> Result<Option<(bool, SimpleMeta, Vec<u8>)>, Error> {
+        let line = serde_json::from_str::<Line>(&log)
+            .map_err(|err| Error::Corrupt(format!("invalid log line: {}", err)))?;
+
+        match (line.action, line.id, line.value) {
+            (Action::Insert, Some(id), Some(value)) => {
+                Ok(Some(self.insert(Operation::new().id(id).value(value))?))
             }
-            Action::Delete => {
-                self.delete(Operation::new().id(line.id.unwrap()).delete());
-                None
+            (Action::Delete, Some(id), _) => {
+                self.delete(Operation::new().id(id).delete())?;
+                Ok(None)
             }
-            Action::Clear => {
+            (Action::Clear, _, _) => {
                 self.clear();
-                None
+                Ok(None)
             }
+            _ => Err(Error::Corrupt(format!("incomplete log line: {}", log))),
         }
     }
 
     fn insert_meta(&mut self, id: Id, mut meta: SimpleMeta) -> (bool, SimpleMeta) {
-        let mut append = false;
-        let return_meta;
+        if let Some(replaced) = self.metas.remove(&id) {
+            self.deleted.push(replaced);
+        }
 
         if let Some(mut old_meta) = self.find_in_deleted(meta.size()) {
             old_meta.set_real(meta.real());
-            return_meta = old_meta.clone();
-            self.metas.insert(id, old_meta);
-        } else if self.size > 0 {
-            meta.set_offset(self.size);
-            return_meta = meta.clone();
-            self.metas.insert(id, meta);
-            append = true;
-        } else {
-            return_meta = meta.clone();
-            self.metas.insert(id, meta);
-            append = true;
+            old_meta.set_checksum(meta.checksum());
+            old_meta.set_compressed(meta.is_compressed());
+            self.metas.insert(id, old_meta.clone());
+            return (false, old_meta);
         }
 
-        self.size += return_meta.size();
+        meta.set_offset(self.size);
+        self.size += meta.size();
+        self.metas.insert(id, meta.clone());
 
-        (append, return_meta)
+        (true, meta)
     }
 
-    fn get_bytes(&self, operation: &Operation) -> Option<Vec<u8>> {
-        if operation.value.is_some() {
-            let data = operation.value.as_ref().unwrap().to_string();
-
-            Some(if BINENCODE {
-                bin_encode(data)
-            } else {
-                data.as_bytes().to_owned()
-            })
-        } else {
-            None
-        }
+    fn get_bytes(&self, operation: &Operation) -> Option<(Vec<u8>, bool)> {
+        let compress = is_compressed(&self.struct_name);
+        operation.value.as_ref().map(|value| codec::pack_record(value, self.version, compress))
     }
 
     fn find_in_deleted(&mut self, size: usize) -> Option<SimpleMeta> {
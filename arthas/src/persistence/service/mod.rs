@@ -5,7 +5,7 @@ use error::Error;
 use super::logger::Logger;
 use store::is_persistence;
 use traits::Structure;
-use loader::{persistence_exits, create_persistence};
+use loader::{persistence_exits, create_persistence, repair_log};
 
 
 pub struct PersistenceService {
@@ -13,34 +13,36 @@ pub struct PersistenceService {
 }
 
 impl PersistenceService {
-    pub fn new<T: Structure>() -> PersistenceService {
+    pub fn new<T: Structure>() -> Result<PersistenceService, Error> {
         let struct_name = T::get_struct_name();
 
         if is_persistence() && !persistence_exits(&struct_name) {
-            create_persistence(&struct_name, &T::get_field_int_map());
+            create_persistence(&struct_name, &T::get_field_int_map())?;
         }
 
-        PersistenceService {
+        Ok(PersistenceService {
             logger: if is_persistence() {
-                Some(Logger::new(&struct_name))
+                repair_log(&struct_name)?;
+                Some(Logger::new(&struct_name)?)
             } else {
                 None
             },
-        }
+        })
     }
 
     pub fn insert(&mut self, id: Id, value: Value) -> Result<(), Error> {
-        self.logger.as_mut().unwrap().insert(id, value);
-        Ok(())
+        self.logger.as_mut().unwrap().insert(id, value)
+    }
+
+    pub fn insert_line(&mut self, line: &str) -> Result<(), Error> {
+        self.logger.as_mut().unwrap().write(line)
     }
 
     pub fn delete(&mut self, id: Id) -> Result<(), Error> {
-        self.logger.as_mut().unwrap().delete(id);
-        Ok(())
+        self.logger.as_mut().unwrap().delete(id)
     }
 
     pub fn clear(&mut self) -> Result<(), Error> {
-        self.logger.as_mut().unwrap().clear();
-        Ok(())
+        self.logger.as_mut().unwrap().clear()
     }
 }
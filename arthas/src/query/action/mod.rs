@@ -8,6 +8,7 @@ pub enum Action {
     Replace,
     Update,
     Remove,
+    Watch,
 }
 
 impl Default for Action {
@@ -6,15 +6,15 @@ mod action;
 #[macro_use]
 mod macros {
     macro_rules! exec_query {
-        ($store:ident, $access:ident, $action:ident, $query: ident) => {
+        ($memory:ident, $action:ident, $query: ident) => {
             {
                 check_query(&$query)?;
 
                 thread_trace!("{:?}: {}, lock done", $query.action, $query.struct_name);
 
-                let result = $store.get_memory::<T>()
-                    .$access()
-                    .unwrap().$action(&mut $query);
+                let result = $memory.$action(&mut $query);
+
+                committer::wait()?;
 
                 thread_trace!("{:?}: {}, query time: {} ms, result: {:?}",
                             $query.action,
@@ -31,13 +31,14 @@ mod macros {
 pub use self::order::Order;
 pub use self::query_type::QueryType;
 
-use std::sync::RwLockReadGuard;
+use std::sync::Arc;
+use std::sync::mpsc::Receiver;
 use std::collections::HashMap;
 use std::time::Instant;
 use item::{Id, StructName, get_len_field_int};
 use memory::Memory;
 use traits::Structure;
-use store::{MemoryStore, memories, persistences, is_persistence, MemoryGetter};
+use store::{memories, persistences, creating, is_persistence, get_memory};
 use std::sync::RwLock;
 use persistence::Persistence;
 use error::Error;
@@ -48,6 +49,8 @@ use to_value;
 use serde::Serialize;
 use traits::get_unique_int_str;
 use self::action::Action;
+use watch::Event;
+use persistence::committer;
 
 
 pub type Updater<'a, T> = Box<Fn(&mut T) + 'a>;
@@ -73,6 +76,8 @@ pub struct Query<'a, T> {
     pub conditions: HashMap<String, Vec<Comparision>>,
     #[doc(hidden)]
     pub orders: Vec<(String, Order)>,
+    #[doc(hidden)]
+    pub text: Option<String>,
     struct_name: StructName,
     action: Action,
     start_time: Option<Instant>,
@@ -120,6 +125,12 @@ impl<'a, T: Structure> Query<'a, T> {
         self
     }
 
+    /// This method tests for strings to be equal ignoring case.
+    pub fn ieq<V: Serialize>(mut self, other: V) -> Query<'a, T> {
+        self.compare(Cmp::Ieq, other);
+        self
+    }
+
     /// This method tests greater than (for `self` and `other`) and is used by the `>` operator.
     pub fn gt<V: Serialize>(mut self, other: V) -> Query<'a, T> {
         self.compare(Cmp::Gt, other);
@@ -150,29 +161,35 @@ impl<'a, T: Structure> Query<'a, T> {
         self
     }
 
-    /// Query order desc field.
+    /// Query order desc field, `len(field)` orders by the field's length.
     pub fn desc<I: AsRef<str>>(mut self, field: I) -> Query<'a, T> {
-        self.orders.push((get_unique_int_str(field.as_ref()), Order::Desc));
+        self.orders.push((get_order_field_int(field.as_ref()), Order::Desc));
         self
     }
 
-    /// Query order asc field.
+    /// Query order asc field, `len(field)` orders by the field's length.
     pub fn asc<I: AsRef<str>>(mut self, field: I) -> Query<'a, T> {
-        self.orders.push((get_unique_int_str(field.as_ref()), Order::Asc));
+        self.orders.push((get_order_field_int(field.as_ref()), Order::Asc));
+        self
+    }
+
+    /// Full-text search on the text fields, items are ranked by relevance unless ordered.
+    pub fn text<I: AsRef<str>>(mut self, text: I) -> Query<'a, T> {
+        self.text = Some(text.as_ref().into());
         self
     }
 
     /// Insert an item.
     pub fn insert(mut self, item: T) -> Result<Id, Error> {
         self.item = Some(item);
-        let store = self.prepare(Action::Insert)?;
-        exec_query!(store, write, insert, self)
+        let memory = self.prepare(Action::Insert)?;
+        exec_query!(memory, insert, self)
     }
 
     /// Remove item.
     pub fn remove(mut self) -> Result<Vec<T>, Error> {
-        let store = self.prepare(Action::Remove)?;
-        exec_query!(store, write, delete, self)
+        let memory = self.prepare(Action::Remove)?;
+        exec_query!(memory, delete, self)
     }
 
     /// Update items.
@@ -180,38 +197,58 @@ impl<'a, T: Structure> Query<'a, T> {
         where F: Fn(&mut T) + 'a
     {
         self.updater = Some(Box::new(updater));
-        let store = self.prepare(Action::Update)?;
-        exec_query!(store, write, update, self)
+        let memory = self.prepare(Action::Update)?;
+        exec_query!(memory, update, self)
     }
 
     /// Replace an item.
     pub fn replace(mut self, item: T) -> Result<(), Error> {
         self.item = Some(item);
-        let store = self.prepare(Action::Replace)?;
-        exec_query!(store, write, replace, self)
+        let memory = self.prepare(Action::Replace)?;
+        exec_query!(memory, replace, self)
     }
 
     /// Find items.
     pub fn find(mut self) -> Result<Vec<T>, Error> {
-        let store = self.prepare(Action::Find)?;
-        exec_query!(store, read, find, self)
+        let memory = self.prepare(Action::Find)?;
+        let snapshot = exec_query!(memory, find, self)?;
+
+        Ok(snapshot.decode::<T>()?
+            .into_iter()
+            .map(|wrapper| wrapper.item)
+            .collect())
     }
 
     /// Find only one item.
     pub fn find_one(mut self) -> Result<Option<T>, Error> {
-        let store = self.prepare(Action::FindOne)?;
-        exec_query!(store, read, find_one, self)
+        let memory = self.prepare(Action::FindOne)?;
+        let snapshot = exec_query!(memory, find_one, self)?;
+        let mut wrappers = snapshot.decode::<T>()?;
+
+        if !wrappers.is_empty() {
+            Ok(Some(wrappers.remove(0).item))
+        } else if T::is_one() {
+            Ok(Some(T::new_empty()))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Watch items matching the conditions, their changes are received as events.
+    pub fn watch(mut self) -> Result<Receiver<Event<T>>, Error> {
+        let memory = self.prepare(Action::Watch)?;
+        exec_query!(memory, watch, self)
     }
 
     /// Count items.
     pub fn count(mut self) -> Result<usize, Error> {
-        let store = self.prepare(Action::Count)?;
+        let memory = self.prepare(Action::Count)?;
         self.orders.clear();
-        exec_query!(store, read, count, self)
+        exec_query!(memory, count, self)
     }
 
     #[inline]
-    fn prepare<'b>(&mut self, action: Action) -> Result<RwLockReadGuard<'b, MemoryStore>, Error> {
+    fn prepare(&mut self, action: Action) -> Result<Arc<Memory>, Error> {
         self.action = action;
         self.start_time = Some(Instant::now());
         thread_trace!("{:?}: {}, query: {:?}, wait for lock.",
@@ -221,28 +258,32 @@ impl<'a, T: Structure> Query<'a, T> {
         self.get_memory()
     }
 
-    fn get_memory<'b>(&self) -> Result<RwLockReadGuard<'b, MemoryStore>, Error> {
+    fn get_memory(&self) -> Result<Arc<Memory>, Error> {
         let struct_name = T::get_struct_name();
-        let store_lock = memories();
-
-        if !store_lock.read().unwrap().contains_key(&struct_name) {
-            if is_persistence() {
-                let persistence_lock = persistences();
-                persistence_lock.write()
-                    .unwrap()
-                    .entry(struct_name.clone())
-                    .or_insert_with(|| {
-                        RwLock::new(Persistence::new(struct_name.clone(), T::get_field_int_map()))
-                    });
-            }
 
-            store_lock.write()
+        if let Some(memory) = get_memory(&struct_name) {
+            return Ok(memory);
+        }
+
+        if is_persistence() {
+            let persistence_lock = persistences();
+            persistence_lock.write()
                 .unwrap()
                 .entry(struct_name.clone())
-                .or_insert_with(|| RwLock::new(Memory::new::<T>()));
+                .or_insert_with(|| {
+                    RwLock::new(Persistence::new(struct_name.clone(), T::get_field_int_map()))
+                });
         }
 
-        Ok(store_lock.read().unwrap())
+        let _creating = creating().lock().unwrap();
+
+        if let Some(memory) = get_memory(&struct_name) {
+            return Ok(memory);
+        }
+
+        let memory = Arc::new(Memory::new::<T>()?);
+        memories().write().unwrap().insert(struct_name, memory.clone());
+        Ok(memory)
     }
 
     #[inline]
@@ -270,18 +311,28 @@ impl<'a, T: Structure> Query<'a, T> {
 impl<'a, T: Structure> fmt::Debug for Query<'a, T> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f,
-               "{{ item: {:?}, id: {:?}, limit: {:?}, field: {:?}, conditions: {:?}, orders: {:?} \
-                }}",
+               "{{ item: {:?}, id: {:?}, limit: {:?}, field: {:?}, conditions: {:?}, orders: {:?}, \
+                text: {:?} }}",
                self.item,
                self.id,
                self.limit,
                self.field,
                self.conditions,
-               self.orders)
+               self.orders,
+               self.text)
     }
 }
 
 
+#[inline]
+fn get_order_field_int(field: &str) -> String {
+    if field.starts_with("len(") && field.ends_with(')') {
+        get_len_field_int(&get_unique_int_str(&field[4..field.len() - 1]))
+    } else {
+        get_unique_int_str(field)
+    }
+}
+
 #[inline]
 fn check_query<T: Structure>(query: &Query<T>) -> Result<(), Error> {
     match query.action {
@@ -1,19 +1,38 @@
 
 use std::path::PathBuf;
+use std::collections::HashSet;
 use std::env;
+use std::time::Duration;
 use DATA_DIR;
+use config::{Durability, Corruption};
 
 
 pub struct Config {
     pub persistence: bool,
+    pub durability: Durability,
+    pub corruption: Corruption,
     pub path: PathBuf,
+    pub sweep_interval: Duration,
+    pub compaction_threshold: f64,
+    pub checkpoint_interval: Duration,
+    pub checkpoint_size: u64,
+    pub compression: bool,
+    pub compressed: HashSet<String>,
 }
 
 impl Config {
     pub fn new() -> Config {
         Config {
             persistence: true,
+            durability: Durability::None,
+            corruption: Corruption::Fail,
             path: get_path(),
+            sweep_interval: Duration::from_secs(1),
+            compaction_threshold: 0.5,
+            checkpoint_interval: Duration::from_secs(60),
+            checkpoint_size: 64 * 1024 * 1024,
+            compression: false,
+            compressed: HashSet::new(),
         }
     }
 }
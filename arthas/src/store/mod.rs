@@ -1,29 +1,21 @@
 
 mod config;
 
-use std::sync::{RwLock, Once, ONCE_INIT};
+use std::sync::{Arc, RwLock, Mutex, Once, ONCE_INIT};
 use std::mem;
 use std::collections::HashMap;
 use std::path::PathBuf;
+use std::time::Duration;
 use self::config::Config;
 use memory::Memory;
 use persistence::Persistence;
 use traits::Structure;
+use config::{Durability, Corruption};
 
 
-pub type MemoryStore = HashMap<String, RwLock<Memory>>;
+pub type MemoryStore = HashMap<String, Arc<Memory>>;
 pub type PersistenceStore = HashMap<String, RwLock<Persistence>>;
 
-pub trait MemoryGetter {
-    fn get_memory<T: Structure>(&self) -> &RwLock<Memory>;
-}
-
-impl MemoryGetter for MemoryStore {
-    fn get_memory<T: Structure>(&self) -> &RwLock<Memory> {
-        self.get(&T::get_struct_name()).unwrap()
-    }
-}
-
 
 pub fn memories<'a>() -> &'a RwLock<MemoryStore> {
     static mut MEMORIES: *const RwLock<MemoryStore> = 0 as *const RwLock<MemoryStore>;
@@ -35,6 +27,24 @@ pub fn memories<'a>() -> &'a RwLock<MemoryStore> {
     }
 }
 
+/// Memories are created one at a time while holding it, so the store lock is only taken to
+/// insert them.
+pub fn creating<'a>() -> &'a Mutex<()> {
+    static mut CREATING: *const Mutex<()> = 0 as *const Mutex<()>;
+    static ONCE: Once = ONCE_INIT;
+
+    unsafe {
+        ONCE.call_once(|| CREATING = mem::transmute(Box::new(Mutex::new(()))));
+        &*CREATING
+    }
+}
+
+/// Memory of the struct, the store lock is released before it is used.
+#[inline]
+pub fn get_memory(struct_name: &str) -> Option<Arc<Memory>> {
+    memories().read().unwrap().get(struct_name).cloned()
+}
+
 pub fn persistences<'a>() -> &'a RwLock<PersistenceStore> {
     static mut PERSISTENCES: *const RwLock<PersistenceStore> = 0 as *const RwLock<PersistenceStore>;
     static ONCE: Once = ONCE_INIT;
@@ -62,11 +72,54 @@ pub fn is_persistence() -> bool {
     config().read().unwrap().persistence
 }
 
+#[inline]
+pub fn get_durability() -> Durability {
+    config().read().unwrap().durability.clone()
+}
+
+#[inline]
+pub fn get_corruption() -> Corruption {
+    config().read().unwrap().corruption.clone()
+}
+
 #[inline]
 pub fn get_path() -> PathBuf {
     config().read().unwrap().path.clone()
 }
 
+#[inline]
+pub fn get_sweep_interval() -> Duration {
+    config().read().unwrap().sweep_interval
+}
+
+#[inline]
+pub fn get_compaction_threshold() -> f64 {
+    config().read().unwrap().compaction_threshold
+}
+
+#[inline]
+pub fn get_checkpoint_interval() -> Duration {
+    config().read().unwrap().checkpoint_interval
+}
+
+#[inline]
+pub fn get_checkpoint_size() -> u64 {
+    config().read().unwrap().checkpoint_size
+}
+
+/// Records are encoded by struct name, so structs with `#[arthas(compress)]` are remembered.
+pub fn register_compression<T: Structure>() {
+    if T::is_compressed() {
+        config().write().unwrap().compressed.insert(T::get_struct_name());
+    }
+}
+
+#[inline]
+pub fn is_compressed(struct_name: &str) -> bool {
+    let config = config().read().unwrap();
+    config.compression || config.compressed.contains(struct_name)
+}
+
 #[inline]
 pub fn get_extension_path(struct_name: &str, ext: &'static str) -> PathBuf {
     PathBuf::from(format!("{}/{}.{}", get_path().display(), struct_name, ext))
@@ -9,12 +9,20 @@ pub trait Arthas: Sized {
     fn get_field_type_map() -> FieldTypeMap;
     fn get_field_int_map() -> FieldIntMap;
     fn is_one() -> bool;
+    fn is_compressed() -> bool;
     fn new_empty() -> Self;
     fn new_deep_empty() -> Self;
     fn has_id() -> bool;
     fn set_id(&mut self, id: Id);
     fn get_id(&self) -> Id;
     fn get_rename_map() -> HashMap<String, String>;
+    fn get_unique_fields() -> Vec<String>;
+    fn get_index_fields() -> Vec<String>;
+    fn get_compound_indexes() -> Vec<Vec<String>>;
+    fn get_hash_index_fields() -> Vec<String>;
+    fn get_collate_fields() -> Vec<(String, String)>;
+    fn get_text_fields() -> Vec<String>;
+    fn get_ttl_field() -> Option<(String, u64)>;
 }
 
 pub type FieldTypeMap = HashMap<String, FieldType>;
@@ -7,4 +7,5 @@ pub enum Cmp {
     Lt,
     Ge,
     Le,
+    Ieq,
 }
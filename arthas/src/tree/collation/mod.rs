@@ -0,0 +1,75 @@
+use std::collections::HashMap;
+use serde_json::Value;
+use unicode_normalization::UnicodeNormalization;
+use unicode_normalization::char::is_combining_mark;
+use item::FieldInt;
+
+
+pub type Collations = HashMap<FieldInt, Collation>;
+
+/// Strings of a collated field are indexed and compared by their collation key.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Collation {
+    NoCase,
+    Unicode,
+}
+
+impl Collation {
+    pub fn new(name: &str) -> Collation {
+        match name {
+            "nocase" => Collation::NoCase,
+            "unicode" => Collation::Unicode,
+            _ => panic!("unsupported collation: {}", name),
+        }
+    }
+
+    pub fn collate(&self, value: &Value) -> Value {
+        match *value {
+            Value::String(ref string) => Value::String(self.get_key(string)),
+            Value::Array(ref values) => {
+                Value::Array(values.iter().map(|value| self.collate(value)).collect())
+            }
+            _ => value.clone(),
+        }
+    }
+
+    /// Unicode keys order by the letters without marks and case first, the composed string
+    /// breaks ties, so composed and decomposed forms of a string have the same key.
+    fn get_key(&self, string: &str) -> String {
+        match *self {
+            Collation::NoCase => string.to_lowercase(),
+            Collation::Unicode => {
+                let mut key = String::with_capacity(string.len() * 2 + 1);
+
+                for ch in string.nfd().filter(|&ch| !is_combining_mark(ch)) {
+                    fold(ch, &mut key);
+                }
+
+                key.push('\u{0}');
+                key.extend(string.nfc());
+                key
+            }
+        }
+    }
+}
+
+/// Lowercase the letter, letters without a decomposition are folded to their base letters.
+fn fold(ch: char, key: &mut String) {
+    match ch {
+        'Æ' | 'æ' => key.push_str("ae"),
+        'Þ' | 'þ' => key.push_str("th"),
+        'ß' => key.push_str("ss"),
+        'Ĳ' | 'ĳ' => key.push_str("ij"),
+        'Œ' | 'œ' => key.push_str("oe"),
+        'Ð' | 'ð' | 'Đ' | 'đ' => key.push('d'),
+        'Ħ' | 'ħ' => key.push('h'),
+        'ı' => key.push('i'),
+        'ĸ' => key.push('k'),
+        'Ŀ' | 'ŀ' | 'Ł' | 'ł' => key.push('l'),
+        'Ŋ' | 'ŋ' => key.push('n'),
+        'Ø' | 'ø' => key.push('o'),
+        'ſ' => key.push('s'),
+        'Ŧ' | 'ŧ' => key.push('t'),
+        _ => key.extend(ch.to_lowercase()),
+    }
+}
@@ -0,0 +1,181 @@
+use serde_json::{Map, Value};
+use item::{FieldInt, get_compound_field_int};
+use traits::get_unique_int_str;
+use query::Order;
+use super::cmp::Cmp;
+use super::comparision::Comparision;
+use super::range::{Range, Bound};
+use super::rc::RcData;
+use super::collation::Collations;
+use super::searcher::task::Task;
+
+
+#[derive(Debug, Clone)]
+pub struct Compound {
+    pub field_int: FieldInt,
+    pub field_ints: Vec<FieldInt>,
+}
+
+impl Compound {
+    pub fn new(fields: &[String]) -> Compound {
+        let field_ints = fields.iter()
+            .map(|field| get_unique_int_str(field))
+            .collect::<Vec<_>>();
+
+        Compound {
+            field_int: get_compound_field_int(&field_ints),
+            field_ints: field_ints,
+        }
+    }
+
+    /// Tuple of the field values, only when every field is a number or a string.
+    pub fn create_rc_data(&self,
+                          object: &Map<String, Value>,
+                          collations: &Collations)
+                          -> Option<RcData> {
+        let mut values = Vec::new();
+
+        for field_int in &self.field_ints {
+            match object.get(field_int) {
+                Some(value) if value.is_number() || value.is_string() => {
+                    values.push(match collations.get(field_int) {
+                        Some(collation) => collation.collate(value),
+                        None => value.clone(),
+                    })
+                }
+                _ => return None,
+            }
+        }
+
+        Some(RcData::new(Value::Array(values)))
+    }
+}
+
+
+/// Search plan over a compound index: an equality prefix, then bounds on the next field.
+#[derive(Debug)]
+pub struct Plan {
+    pub field_int: FieldInt,
+    pub range: Range,
+    pub desc: bool,
+    pub sorted: bool,
+    pub covered: Vec<FieldInt>,
+    score: usize,
+}
+
+impl Plan {
+    pub fn new(compounds: &[Compound], task: &Task) -> Option<Plan> {
+        let mut best: Option<Plan> = None;
+
+        for compound in compounds {
+            if let Some(plan) = Self::create(compound, task) {
+                if best.as_ref().map_or(true, |best| plan.score > best.score) {
+                    best = Some(plan);
+                }
+            }
+        }
+
+        best
+    }
+
+    pub fn covers(&self, field_int: &str) -> bool {
+        self.covered.iter().any(|covered| covered == field_int)
+    }
+
+    fn create(compound: &Compound, task: &Task) -> Option<Plan> {
+        let mut prefix = Vec::new();
+        let mut covered = Vec::new();
+
+        for field_int in &compound.field_ints {
+            match get_comparisions(task, field_int) {
+                Some(comparisions) if comparisions.len() == 1 &&
+                                      comparisions[0].cmp == Cmp::Eq => {
+                    prefix.push(comparisions[0].other.clone());
+                    covered.push(field_int.clone());
+                }
+                _ => break,
+            }
+        }
+
+        if prefix.is_empty() {
+            return None;
+        }
+
+        let mut lower = Bound::new(Value::Array(prefix.clone()), true);
+        let mut upper = Bound::new(Value::Array(prefix.clone()), true);
+        let mut suffix_used = false;
+        let mut sorted = false;
+        let mut desc = false;
+
+        if let Some(suffix) = compound.field_ints.get(prefix.len()) {
+            let bounds = get_comparisions(task, suffix).and_then(get_suffix_bounds);
+
+            if let Some((lower_option, upper_option)) = bounds {
+                if let Some((value, inclusive)) = lower_option {
+                    let mut values = prefix.clone();
+                    values.push(value);
+                    lower = Bound::new(Value::Array(values), inclusive);
+                }
+
+                if let Some((value, inclusive)) = upper_option {
+                    let mut values = prefix.clone();
+                    values.push(value);
+                    upper = Bound::new(Value::Array(values), inclusive);
+                }
+
+                covered.push(suffix.clone());
+                suffix_used = true;
+            }
+
+            if task.orders.len() == 1 && &task.orders[0].0 == suffix {
+                sorted = true;
+                desc = task.orders[0].1 == Order::Desc;
+            }
+        }
+
+        if prefix.len() < 2 && !suffix_used && !sorted {
+            return None;
+        }
+
+        Some(Plan {
+            field_int: compound.field_int.clone(),
+            range: Range::between(lower, upper),
+            desc: desc,
+            sorted: sorted,
+            covered: covered,
+            score: prefix.len() * 2 + suffix_used as usize + sorted as usize,
+        })
+    }
+}
+
+fn get_comparisions<'a>(task: &'a Task, field_int: &str) -> Option<&'a [Comparision]> {
+    task.subs
+        .iter()
+        .find(|sub| sub.field_int == field_int)
+        .map(|sub| &sub.comparisions[..])
+}
+
+/// Lower and upper bound of the suffix field, `None` if the comparisions can not be ranged.
+fn get_suffix_bounds(comparisions: &[Comparision])
+                     -> Option<(Option<(Value, bool)>, Option<(Value, bool)>)> {
+    let mut lower = None;
+    let mut upper = None;
+
+    for comparision in comparisions {
+        let value = comparision.other.clone();
+
+        match comparision.cmp {
+            Cmp::Eq if lower.is_none() && upper.is_none() => {
+                lower = Some((value.clone(), true));
+                upper = Some((value, true));
+            }
+            Cmp::Gt if lower.is_none() => lower = Some((value, false)),
+            Cmp::Ge if lower.is_none() => lower = Some((value, true)),
+            Cmp::Lt if upper.is_none() => upper = Some((value, false)),
+            Cmp::Le if upper.is_none() => upper = Some((value, true)),
+            _ => return None,
+        }
+    }
+
+    Some((lower, upper))
+}
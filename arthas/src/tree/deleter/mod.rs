@@ -1,4 +1,4 @@
-
+use super::node;
 use super::Tree;
 
 
@@ -14,27 +14,59 @@ impl Deleter {
 
         let rc_item = rc_item.unwrap();
 
-        for (field_int, rc_node) in &rc_item.read().unwrap().nodes {
-            let (clear, deleted_rc_node, root) = rc_node.write().unwrap().delete(id);
-            if clear {
-                let rc_node = tree.root.remove(field_int);
-                if rc_node.is_some() {
-                    rc_node.unwrap().destroy();
-                }
-            } else if deleted_rc_node.is_some() {
-                deleted_rc_node.unwrap().destroy();
+        for (field_int, hash_index) in &mut tree.hashes {
+            if let Some(rc_data) = rc_item.read().unwrap().datas.get(field_int) {
+                hash_index.delete(rc_data.read().unwrap().get_value(), id);
             }
+        }
 
-            if root.is_some() {
-                tree.root.insert(field_int.clone(), root.unwrap());
-            }
+        for text_index in tree.texts.values_mut() {
+            text_index.delete(id);
         }
 
-        rc_item.destroy();
+        let datas = rc_item.read().unwrap().datas.clone();
+
+        for (field_int, rc_data) in datas {
+            if !tree.is_linked(&field_int, &rc_data) {
+                continue;
+            }
+
+            let value = rc_data.read().unwrap().get_value().clone();
+            let deleted = tree.root
+                .get(&field_int)
+                .and_then(|root| node::delete(root, id, &value));
+
+            let (root, unlinked) = match deleted {
+                Some(deleted) => deleted,
+                None => continue,
+            };
+
+            match root {
+                Some(root) => tree.root.insert(field_int.clone(), root),
+                None => tree.root.remove(&field_int),
+            };
+
+            if let Some(stats) = tree.stats.get_mut(&field_int) {
+                stats.children -= 1;
+
+                if unlinked {
+                    stats.distinct -= 1;
+                }
+            }
+        }
     }
 
     pub fn clear(tree: &mut Tree) {
         tree.id_map.clear();
         tree.root.clear();
+        tree.stats.clear();
+
+        for hash_index in tree.hashes.values_mut() {
+            hash_index.clear();
+        }
+
+        for text_index in tree.texts.values_mut() {
+            text_index.clear();
+        }
     }
 }
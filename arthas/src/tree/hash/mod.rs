@@ -0,0 +1,88 @@
+use serde_json::Value;
+use im::HashMap;
+use item::Id;
+use super::node::Group;
+use super::rc::RcChild;
+
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum HashKey {
+    Unsigned(u64),
+    Signed(i64),
+    Float(u64),
+    String(String),
+}
+
+impl HashKey {
+    /// Integral floats are keyed as integers, so numbers match like `Math::eq`.
+    pub fn new(value: &Value) -> Option<HashKey> {
+        match *value {
+            Value::Number(ref number) => {
+                if let Some(number) = number.as_u64() {
+                    return Some(HashKey::Unsigned(number));
+                } else if let Some(number) = number.as_i64() {
+                    return Some(HashKey::Signed(number));
+                }
+
+                let number = number.as_f64().unwrap();
+
+                if number.trunc() == number && number >= 0.0 &&
+                   number < 18446744073709551616.0 {
+                    Some(HashKey::Unsigned(number as u64))
+                } else if number.trunc() == number && number >= -9223372036854775808.0 &&
+                          number < 0.0 {
+                    Some(HashKey::Signed(number as i64))
+                } else {
+                    Some(HashKey::Float(number.to_bits()))
+                }
+            }
+            Value::String(ref string) => Some(HashKey::String(string.to_owned())),
+            _ => None,
+        }
+    }
+}
+
+
+#[derive(Default, Clone)]
+pub struct HashIndex {
+    pub groups: HashMap<HashKey, Group>,
+}
+
+impl HashIndex {
+    pub fn new() -> HashIndex {
+        HashIndex { ..Default::default() }
+    }
+
+    pub fn insert(&mut self, value: &Value, id: Id, rc_child: RcChild) {
+        if let Some(key) = HashKey::new(value) {
+            self.groups.entry(key).or_default().insert(id, rc_child);
+        }
+    }
+
+    pub fn get(&self, value: &Value) -> Option<Group> {
+        HashKey::new(value).and_then(|key| self.groups.get(&key).cloned())
+    }
+
+    pub fn delete(&mut self, value: &Value, id: &str) {
+        let key = match HashKey::new(value) {
+            Some(key) => key,
+            None => return,
+        };
+
+        let is_empty = match self.groups.get_mut(&key) {
+            Some(group) => {
+                group.remove(id);
+                group.is_empty()
+            }
+            None => return,
+        };
+
+        if is_empty {
+            self.groups.remove(&key);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.groups.clear();
+    }
+}
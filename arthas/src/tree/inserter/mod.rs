@@ -1,60 +1,53 @@
-
-use item::Id;
-use serde_json::Value;
-use super::rc::{RcItem, RcChild, RcNode};
+use super::rc::{RcItem, RcChild};
+use super::node;
 use super::Tree;
+use super::deleter::Deleter;
+use super::stats::Stats;
 
 pub struct Inserter {}
 
 impl Inserter {
-    pub fn insert(tree: &mut Tree, id: Id, value: Value) {
+    pub fn insert(tree: &mut Tree, rc_item: RcItem) {
+        let id = rc_item.read().unwrap().id.clone();
         thread_trace!("insert id: {}", id);
-        let rc_item = RcItem::new(id.clone(), value);
+
+        // An item with the id of an existing one replaces it, the old one is unlinked first
+        // so it is not counted twice.
+        if tree.id_map.contains_key(&id) {
+            Deleter::delete(tree, &id);
+        }
+
         tree.id_map.insert(id.clone(), rc_item.clone());
         let datas = rc_item.read().unwrap().datas.clone();
 
+        for (field_int, text_index) in &mut tree.texts {
+            if let Some(value) = rc_item.read().unwrap().value["item"].get(field_int) {
+                text_index.insert(value, id.clone());
+            }
+        }
+
         for (field_int, rc_data) in datas {
-            if rc_data.read().unwrap().can_index() {
-                let mut is_min = true;
-                let mut is_max = true;
+            if let Some(hash_index) = tree.hashes.get_mut(&field_int) {
+                let value = rc_data.read().unwrap().get_value().clone();
+                let rc_child = RcChild::new(rc_data.clone(), rc_item.clone());
+                hash_index.insert(&value, id.clone(), rc_child);
+
+                continue;
+            }
 
+            if tree.is_linked(&field_int, &rc_data) {
                 thread_trace!("current insert field data: {:?}",
                               rc_data.read().unwrap().get_value());
 
                 let rc_child = RcChild::new(rc_data, rc_item.clone());
-                let root_exists = tree.root.contains_key(&field_int);
-
-                if root_exists {
-                    let node_option = tree.root
-                        .get(&field_int)
-                        .unwrap()
-                        .write()
-                        .unwrap()
-                        .insert(field_int.clone(),
-                                id.clone(),
-                                rc_child,
-                                &mut is_min,
-                                &mut is_max);
-
-                    if node_option.is_some() {
-                        let rc_node = node_option.unwrap();
-                        if is_min {
-                            thread_trace!("found min node: {:?}",
-                                          rc_node.read().unwrap().get_value());
-                            tree.min.insert(field_int, rc_node);
-                        } else if is_max {
-                            thread_trace!("found max node: {:?}",
-                                          rc_node.read().unwrap().get_value());
-                            tree.max.insert(field_int, rc_node);
-                        } else {
-                            unreachable!()
-                        }
-                    }
-                } else {
-                    let rc_node = RcNode::new(id.clone(), rc_child);
-                    rc_node.write().unwrap().self_rc = Some(rc_node.clone());
-                    rc_item.write().unwrap().nodes.insert(field_int.clone(), rc_node.clone());
-                    tree.root.insert(field_int, rc_node);
+                let (root, created) = node::insert(tree.root.get(&field_int), id.clone(), rc_child);
+                tree.root.insert(field_int.clone(), root);
+
+                let stats = tree.stats.entry(field_int).or_insert_with(Stats::default);
+                stats.children += 1;
+
+                if created {
+                    stats.distinct += 1;
                 }
             }
         }
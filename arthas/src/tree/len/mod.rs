@@ -28,5 +28,5 @@ impl Len for Value {
 
 #[inline]
 fn create_rc_data_by_usize(len: usize) -> Option<RcData> {
-    Some(RcData::new(Box::into_raw(Box::new(Value::Number(serde_json::Number::from(len))))))
+    Some(RcData::new(Value::Number(serde_json::Number::from(len))))
 }
@@ -1,6 +1,6 @@
 
 use std::cmp::Ordering;
-use serde_json::Value;
+use serde_json::{Number, Value};
 use query::Order;
 
 
@@ -12,7 +12,7 @@ pub trait Math {
     fn ge(&self, &Value) -> bool;
     fn le(&self, &Value) -> bool;
     fn cmp(&self, &Value, order: &Order) -> Ordering;
-    fn get_rate(&self, &Value) -> f64;
+    fn ieq(&self, &Value) -> bool;
 }
 
 impl Math for Value {
@@ -20,7 +20,7 @@ impl Math for Value {
         thread_trace!("math eq, current: {:?}, find: {:?}", self, value);
 
         if self.is_number() && value.is_number() {
-            self.get_f64() == value.get_f64()
+            cmp_number(self.get_number(), value.get_number()) == Ordering::Equal
         } else {
             self == value
         }
@@ -30,7 +30,7 @@ impl Math for Value {
         thread_trace!("math ne, current: {:?}, find: {:?}", self, value);
 
         if self.is_number() && value.is_number() {
-            self.get_f64() != value.get_f64()
+            cmp_number(self.get_number(), value.get_number()) != Ordering::Equal
         } else {
             self != value
         }
@@ -40,9 +40,11 @@ impl Math for Value {
         thread_trace!("math gt, current: {:?}, find: {:?}", self, value);
 
         if self.is_number() && value.is_number() {
-            self.get_f64() > value.get_f64()
+            cmp_number(self.get_number(), value.get_number()) == Ordering::Greater
         } else if self.is_string() && value.is_string() {
             self.get_str() > value.get_str()
+        } else if self.is_array() && value.is_array() {
+            cmp_array(self, value) == Ordering::Greater
         } else {
             panic!("Unsupported math type");
         }
@@ -52,9 +54,11 @@ impl Math for Value {
         thread_trace!("math lt, current: {:?}, find: {:?}", self, value);
 
         if self.is_number() && value.is_number() {
-            self.get_f64() < value.get_f64()
+            cmp_number(self.get_number(), value.get_number()) == Ordering::Less
         } else if self.is_string() && value.is_string() {
             self.get_str() < value.get_str()
+        } else if self.is_array() && value.is_array() {
+            cmp_array(self, value) == Ordering::Less
         } else {
             panic!("Unsupported math type");
         }
@@ -64,9 +68,11 @@ impl Math for Value {
         thread_trace!("math ge, current: {:?}, find: {:?}", self, value);
 
         if self.is_number() && value.is_number() {
-            self.get_f64() >= value.get_f64()
+            cmp_number(self.get_number(), value.get_number()) != Ordering::Less
         } else if self.is_string() && value.is_string() {
             self.get_str() >= value.get_str()
+        } else if self.is_array() && value.is_array() {
+            cmp_array(self, value) != Ordering::Less
         } else {
             panic!("Unsupported math type");
         }
@@ -76,9 +82,11 @@ impl Math for Value {
         thread_trace!("math le, current: {:?}, find: {:?}", self, value);
 
         if self.is_number() && value.is_number() {
-            self.get_f64() <= value.get_f64()
+            cmp_number(self.get_number(), value.get_number()) != Ordering::Greater
         } else if self.is_string() && value.is_string() {
             self.get_str() <= value.get_str()
+        } else if self.is_array() && value.is_array() {
+            cmp_array(self, value) != Ordering::Greater
         } else {
             panic!("Unsupported math type");
         }
@@ -108,24 +116,91 @@ impl Math for Value {
         }
     }
 
-    fn get_rate(&self, other: &Value) -> f64 {
-        if self.is_number() && other.is_number() {
-            let n1 = self.get_f64();
-            let n2 = other.get_f64();
-            n2 / n1
-        } else if self.is_string() && other.is_string() {
-            let n1 = self.get_str().chars().map(|c| c as usize).fold(0, |a, b| a + b);
-            let n2 = other.get_str().chars().map(|c| c as usize).fold(0, |a, b| a + b);
-            n2 as f64 / n1 as f64
+    fn ieq(&self, value: &Value) -> bool {
+        thread_trace!("math ieq, current: {:?}, find: {:?}", self, value);
+
+        if self.is_string() && value.is_string() {
+            self.get_str().to_lowercase() == value.get_str().to_lowercase()
+        } else if self.is_array() && value.is_array() {
+            let a = self.as_array().unwrap();
+            let b = value.as_array().unwrap();
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| Math::ieq(a, b))
         } else {
-            panic!("Unsupported math type");
+            Math::eq(self, value)
         }
     }
 }
 
+/// Integers are compared exactly, floats only against the other side's exact value.
+pub fn cmp_number(a: &Number, b: &Number) -> Ordering {
+    if let (Some(a), Some(b)) = (a.as_u64(), b.as_u64()) {
+        a.cmp(&b)
+    } else if let (Some(a), Some(b)) = (a.as_i64(), b.as_i64()) {
+        a.cmp(&b)
+    } else if a.is_u64() && b.is_i64() {
+        Ordering::Greater
+    } else if a.is_i64() && b.is_u64() {
+        Ordering::Less
+    } else if a.is_f64() && b.is_f64() {
+        a.as_f64().unwrap().partial_cmp(&b.as_f64().unwrap()).unwrap_or(Ordering::Equal)
+    } else if a.is_f64() {
+        cmp_integer_float(b, a.as_f64().unwrap()).reverse()
+    } else {
+        cmp_integer_float(a, b.as_f64().unwrap())
+    }
+}
+
+/// Compare the integer with the float's integral part first, then with its fraction.
+fn cmp_integer_float(integer: &Number, float: f64) -> Ordering {
+    let truncated = float.trunc();
+    let fraction = if float > truncated {
+        Ordering::Less
+    } else if float < truncated {
+        Ordering::Greater
+    } else {
+        Ordering::Equal
+    };
+
+    if let Some(integer) = integer.as_u64() {
+        if truncated < 0.0 {
+            Ordering::Greater
+        } else if truncated >= 18446744073709551616.0 {
+            Ordering::Less
+        } else {
+            integer.cmp(&(truncated as u64)).then(fraction)
+        }
+    } else {
+        let integer = integer.as_i64().unwrap();
+
+        if truncated < -9223372036854775808.0 {
+            Ordering::Greater
+        } else if truncated >= 9223372036854775808.0 {
+            Ordering::Less
+        } else {
+            integer.cmp(&(truncated as i64)).then(fraction)
+        }
+    }
+}
+
+/// Lexicographic order of two arrays of numbers or strings.
+fn cmp_array(a: &Value, b: &Value) -> Ordering {
+    let a = a.as_array().unwrap();
+    let b = b.as_array().unwrap();
+
+    for (a_value, b_value) in a.iter().zip(b) {
+        if Math::lt(a_value, b_value) {
+            return Ordering::Less;
+        } else if Math::gt(a_value, b_value) {
+            return Ordering::Greater;
+        }
+    }
+
+    a.len().cmp(&b.len())
+}
+
 
 trait Type {
-    fn get_f64(&self) -> f64;
+    fn get_number(&self) -> &Number;
     fn get_string(&self) -> String;
     fn get_str(&self) -> &str;
     fn get_u64(&self) -> u64;
@@ -135,9 +210,9 @@ trait Type {
 }
 
 impl Type for Value {
-    fn get_f64(&self) -> f64 {
+    fn get_number(&self) -> &Number {
         match *self {
-            Value::Number(ref n) => n.as_f64().unwrap(),
+            Value::Number(ref n) => n,
             _ => panic!("not a number"),
         }
     }
@@ -7,46 +7,110 @@ pub mod deleter;
 pub mod math;
 pub mod comparision;
 pub mod cmp;
-pub mod action;
 pub mod len;
+pub mod compound;
+pub mod hash;
+pub mod range;
+pub mod collation;
+pub mod text;
+pub mod ttl;
+pub mod stats;
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 use serde_json::Value;
+use im::HashMap as SharedMap;
 use traits::{Structure, FieldIntMap};
 use query::{Query, QueryType};
 use error::Error;
-use item::{Id, FieldInt};
-use self::rc::{RcNode, RcItem};
+use item::{Id, FieldInt, get_len_field_int};
+use self::rc::{RcNode, RcItem, RcData};
+use self::node::Group;
+use self::compound::Compound;
+use self::hash::HashIndex;
+use self::collation::{Collation, Collations};
+use self::text::TextIndex;
+use self::ttl::Ttl;
+use self::stats::Stats;
+use self::range::{Range, Bound};
+use to_value;
+use traits::get_unique_int_str;
 use self::inserter::Inserter;
 use self::searcher::Searcher;
 use self::deleter::Deleter;
 use utils::hash_map::revert;
-use scoped_pool::Pool;
 
 
+/// Items of a struct and their indexes. Clones share all they do not change, a change copies
+/// the paths from the roots to what it changes.
+#[derive(Clone)]
 pub struct Tree {
     pub int_field_map: FieldIntMap,
-    pub id_map: HashMap<Id, RcItem>,
+    pub indexes: HashSet<FieldInt>,
+    pub compounds: Vec<Compound>,
+    pub hashes: HashMap<FieldInt, HashIndex>,
+    pub collations: Collations,
+    pub texts: HashMap<FieldInt, TextIndex>,
+    pub ttl: Option<Ttl>,
+    pub id_map: SharedMap<Id, RcItem>,
     pub root: HashMap<FieldInt, RcNode>,
-    pub min: HashMap<FieldInt, RcNode>,
-    pub max: HashMap<FieldInt, RcNode>,
-    pub searcher: Searcher,
+    pub stats: HashMap<FieldInt, Stats>,
 }
 
 impl Tree {
     pub fn new<T: Structure>() -> Tree {
+        let int_field_map = revert(T::get_field_int_map());
+        let mut indexes = get_indexes(&int_field_map, &T::get_index_fields());
+        let compounds = T::get_compound_indexes()
+            .iter()
+            .map(|fields| Compound::new(fields))
+            .collect::<Vec<_>>();
+
+        for compound in &compounds {
+            indexes.insert(compound.field_int.clone());
+        }
+
+        let hashes = T::get_hash_index_fields()
+            .iter()
+            .map(|field| (get_unique_int_str(field), HashIndex::new()))
+            .collect();
+
+        let texts = T::get_text_fields()
+            .iter()
+            .map(|field| (get_unique_int_str(field), TextIndex::new()))
+            .collect();
+
+        let collations = T::get_collate_fields()
+            .iter()
+            .map(|&(ref field, ref name)| (get_unique_int_str(field), Collation::new(name)))
+            .collect();
+
         Tree {
-            int_field_map: revert(T::get_field_int_map()),
-            id_map: HashMap::new(),
+            int_field_map: int_field_map,
+            indexes: indexes,
+            compounds: compounds,
+            hashes: hashes,
+            collations: collations,
+            texts: texts,
+            ttl: T::get_ttl_field().map(|(field, seconds)| Ttl::new(&field, seconds)),
+            id_map: SharedMap::new(),
             root: HashMap::new(),
-            min: HashMap::new(),
-            max: HashMap::new(),
-            searcher: Searcher::new(),
+            stats: HashMap::new(),
         }
     }
 
     pub fn insert(&mut self, id: Id, value: Value) {
-        Inserter::insert(self, id, value);
+        let rc_item = self.create_item(id, value);
+        self.insert_item(rc_item);
+    }
+
+    /// Items are created apart from linking, so it can be done under a shared lock.
+    pub fn create_item(&self, id: Id, value: Value) -> RcItem {
+        RcItem::new(id, value, &self.compounds, &self.collations)
+    }
+
+    pub fn insert_item(&mut self, rc_item: RcItem) {
+        Inserter::insert(self, rc_item);
     }
 
     pub fn delete(&mut self, id: &str) {
@@ -57,11 +121,212 @@ impl Tree {
         Deleter::clear(self);
     }
 
-    pub fn search<T: Structure>(&self,
-                                pool: &Pool,
-                                query: &Query<T>,
-                                query_type: &QueryType)
-                                -> Result<(usize, Vec<*const Value>), Error> {
-        self.searcher.search(pool, self, query, query_type)
+    pub fn get_group(&self, field_int: &str, value: &Value) -> Option<Group> {
+        if let Some(hash_index) = self.hashes.get(field_int) {
+            return hash_index.get(value);
+        }
+
+        self.root.get(field_int).and_then(|rc_node| rc_node.find_group(value))
+    }
+
+    /// Datas of indexed fields are linked in the nodes of the field, unless it is hashed.
+    pub fn is_linked(&self, field_int: &str, rc_data: &RcData) -> bool {
+        !self.hashes.contains_key(field_int) && self.indexes.contains(field_int) &&
+        (rc_data.read().unwrap().can_index() || self.is_compound(field_int))
+    }
+
+    pub fn is_compound(&self, field_int: &str) -> bool {
+        self.compounds.iter().any(|compound| compound.field_int == field_int)
+    }
+
+    /// Ids of the items expired at `now`, found by the index of the ttl field if it has one.
+    pub fn get_expired_ids(&self, now: f64) -> Vec<Id> {
+        let ttl = match self.ttl {
+            Some(ref ttl) => ttl,
+            None => return Vec::new(),
+        };
+
+        match self.root.get(&ttl.field_int) {
+            Some(rc_node) => {
+                let range = Range::between(Bound::new(to_value(0), false),
+                                           Bound::new(to_value(now - ttl.seconds as f64), true));
+                let mut groups = Vec::new();
+                rc_node.search(&range, false, None, &mut groups);
+
+                groups.iter()
+                    .flat_map(|group| group.keys().cloned().collect::<Vec<_>>())
+                    .collect()
+            }
+            None => {
+                self.id_map
+                    .iter()
+                    .filter(|&(_, rc_item)| ttl.is_expired(&rc_item.read().unwrap(), now))
+                    .map(|(id, _)| id.clone())
+                    .collect()
+            }
+        }
+    }
+}
+
+/// Search the trees holding the items of a struct.
+pub fn search<T: Structure>(trees: &[&Tree],
+                            query: &Query<T>,
+                            query_type: &QueryType)
+                            -> Result<(usize, Vec<Arc<Value>>), Error> {
+    Searcher::new().search(trees, query, query_type)
+}
+
+/// Field ints of indexed fields and their `len(...)` fields, nested paths follow their top field.
+fn get_indexes(int_field_map: &FieldIntMap, index_fields: &[String]) -> HashSet<FieldInt> {
+    let mut indexes = HashSet::new();
+
+    for (field_int, path) in int_field_map {
+        let field = path.split('.').next().unwrap();
+
+        if index_fields.iter().any(|index_field| index_field == field) {
+            indexes.insert(field_int.to_owned());
+            indexes.insert(get_len_field_int(field_int));
+        }
+    }
+
+    indexes
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use serde_json::{Map, Value};
+    use to_value;
+    use super::Tree;
+    use super::rc::RcNode;
+    use super::hash::HashIndex;
+
+    const UNIQUE: &'static str = "1";
+    const FEW: &'static str = "2";
+
+    /// `UNIQUE` is indexed by the tree, `FEW` is hashed.
+    fn create_tree(ids: &[usize]) -> Tree {
+        let mut tree = Tree {
+            int_field_map: HashMap::new(),
+            indexes: vec![UNIQUE.to_owned()].into_iter().collect(),
+            compounds: Vec::new(),
+            hashes: vec![(FEW.to_owned(), HashIndex::new())].into_iter().collect(),
+            collations: HashMap::new(),
+            texts: HashMap::new(),
+            ttl: None,
+            id_map: Default::default(),
+            root: HashMap::new(),
+            stats: HashMap::new(),
+        };
+
+        for &id in ids {
+            insert(&mut tree, id);
+        }
+
+        tree
+    }
+
+    fn insert(tree: &mut Tree, id: usize) {
+        insert_value(tree, id, id);
+    }
+
+    fn insert_value(tree: &mut Tree, id: usize, unique: usize) {
+        let mut object = Map::new();
+        object.insert(UNIQUE.to_owned(), to_value(unique));
+        object.insert(FEW.to_owned(), to_value(unique % 4));
+
+        let mut value = Map::new();
+        value.insert("item".to_owned(), Value::Object(object));
+        tree.insert(id.to_string(), Value::Object(value));
+    }
+
+    /// Ids in the order of the nodes, sizes are checked on the way.
+    fn walk(node: &RcNode, ids: &mut Vec<usize>) -> usize {
+        let mut size = node.group.len();
+
+        if let Some(ref left) = node.left {
+            size += walk(left, ids);
+        }
+
+        ids.extend(node.group.keys().cloned().map(parse));
+
+        if let Some(ref right) = node.right {
+            size += walk(right, ids);
+        }
+
+        assert_eq!(node.size, size);
+        size
+    }
+
+    fn parse(id: String) -> usize {
+        id.parse().unwrap()
+    }
+
+    fn check(tree: &Tree, expected: &[usize]) {
+        let mut ids = Vec::new();
+        walk(&tree.root[UNIQUE], &mut ids);
+        assert_eq!(ids, expected);
+
+        let mut keys = tree.id_map.keys().cloned().map(parse).collect::<Vec<_>>();
+        keys.sort();
+        assert_eq!(keys, expected);
+
+        let mut hashed = tree.get_group(FEW, &to_value(0))
+            .map_or(Vec::new(),
+                    |group| group.keys().cloned().map(parse).collect());
+        hashed.sort();
+        assert_eq!(hashed, expected.iter().cloned().filter(|id| id % 4 == 0).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_clone() {
+        let ids = (0..100).collect::<Vec<_>>();
+        let tree = create_tree(&ids);
+        let mut cloned = tree.clone();
+
+        for id in 0..50 {
+            cloned.delete(&id.to_string());
+        }
+
+        for id in 100..120 {
+            insert(&mut cloned, id);
+        }
+
+        check(&tree, &ids);
+        check(&cloned, &(50..120).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_clone_of_clone() {
+        let ids = (0..10).collect::<Vec<_>>();
+        let tree = create_tree(&ids);
+        let mut cloned = tree.clone().clone();
+
+        cloned.clear();
+        insert(&mut cloned, 10);
+
+        check(&tree, &ids);
+        check(&cloned, &[10]);
+    }
+
+    #[test]
+    fn test_insert_existing_id() {
+        let ids = (0..10).collect::<Vec<_>>();
+        let mut tree = create_tree(&ids);
+
+        insert(&mut tree, 3);
+        check(&tree, &ids);
+
+        insert_value(&mut tree, 4, 41);
+        let mut ids = Vec::new();
+        walk(&tree.root[UNIQUE], &mut ids);
+        assert_eq!(ids, vec![0, 1, 2, 3, 5, 6, 7, 8, 9, 4]);
+
+        assert_eq!(tree.root[UNIQUE].size, tree.id_map.len());
+        assert_eq!(tree.stats[UNIQUE].children, 10);
+        assert_eq!(tree.stats[UNIQUE].distinct, 10);
+        assert!(!tree.get_group(FEW, &to_value(0)).unwrap().contains_key("4"));
+        assert!(tree.get_group(FEW, &to_value(1)).unwrap().contains_key("4"));
     }
 }
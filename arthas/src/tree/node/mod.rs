@@ -1,713 +1,613 @@
-
-use std::sync::atomic::Ordering;
-use std::sync::atomic::AtomicBool;
-use std::collections::HashMap;
-use std::sync::Arc;
-use std::sync::RwLock;
+use std::cmp::{self, Ordering};
 use std::fmt;
 use serde_json::Value;
-use item::{Id, FieldInt};
-use super::comparision::Comparision;
-use super::action::SearchAction;
+use im::HashMap;
+use item::Id;
 use super::rc::RcChild;
 use super::math::Math;
 use super::rc::DataType;
 use super::rc::RcNode;
-use super::searcher::task::Sub;
+use super::range::Range;
 
 
-pub type Group = Arc<RwLock<HashMap<Id, RcChild>>>;
+/// Children of a node by their ids, copies share the children they do not change.
+pub type Group = HashMap<Id, RcChild>;
 pub type Groups = Vec<Group>;
 
 
+/// Node of an AVL tree, every node holds the group of children with the same value. Nodes are
+/// never changed once linked, a change copies the path from the root to the changed node.
 #[derive(Clone)]
 pub struct Node {
     pub _type: DataType,
-    pub self_rc: Option<RcNode>,
     pub group: Group,
-    pub parent: Option<RcNode>,
     pub left: Option<RcNode>,
     pub right: Option<RcNode>,
+    pub height: usize,
+    pub size: usize,
 }
 
 impl Node {
     pub fn new(id: Id, rc_child: RcChild) -> Node {
         let _type = rc_child.read().unwrap().get_type();
 
-        let mut map = HashMap::new();
-        map.insert(id, rc_child);
+        let mut group = HashMap::new();
+        group.insert(id, rc_child);
 
         Node {
             _type: _type,
-            self_rc: None,
-            group: Arc::new(RwLock::new(map)),
-            parent: None,
+            group: group,
             left: None,
             right: None,
+            height: 1,
+            size: 1,
         }
     }
 
-    pub fn insert(&mut self,
-                  field_int: FieldInt,
-                  id: Id,
-                  rc_child: RcChild,
-                  is_min: &mut bool,
-                  is_max: &mut bool)
-                  -> Option<RcNode> {
+    /// Compare the value to the value of the group, without copying it.
+    fn cmp_value(&self, value: &Value) -> Ordering {
         match self._type {
-            DataType::Number | DataType::String => {
-                if Math::gt(&rc_child.read().unwrap().get_value(), &self.get_value()) {
-                    if *is_min {
-                        thread_trace!("found gt, cancel min");
-                        *is_min = false;
-                    }
-
-                    if self.right.is_some() {
-                        thread_trace!("continue insert right");
-                        self.insert_continue_right(field_int, id, rc_child, is_min, is_max)
-                    } else {
-                        thread_trace!("set right node");
-                        self.set_right(field_int, id, rc_child, is_max)
-                    }
-                } else if Math::lt(&rc_child.read().unwrap().get_value(), &self.get_value()) {
-                    if *is_max {
-                        thread_trace!("found lt, cancel max");
-                        *is_max = false;
-                    }
-
-                    if self.left.is_some() {
-                        thread_trace!("continue insert left");
-                        self.insert_continue_left(field_int, id, rc_child, is_min, is_max)
-                    } else {
-                        thread_trace!("set left node");
-                        self.set_left(field_int, id, rc_child, is_min)
-                    }
-                } else {
-                    thread_trace!("found group, insert to current");
-                    self.insert_to_current(field_int, id, rc_child);
-                    *is_min = false;
-                    *is_max = false;
-                    None
-                }
-            }
+            DataType::Number | DataType::String | DataType::Array => {}
             _ => unreachable!(),
         }
-    }
-
-    fn insert_to_current(&mut self, field_int: FieldInt, id: Id, rc_child: RcChild) {
-        {
-            let child = rc_child.read().unwrap();
-            child.item
-                .write()
-                .unwrap()
-                .nodes
-                .insert(field_int, self.self_rc.clone().unwrap());
-        }
 
-        self.group.write().unwrap().insert(id, rc_child);
-    }
+        let child = self.group.values().next().unwrap().read().unwrap();
+        let data = child.data.read().unwrap();
+        let current = data.get_value();
 
-    fn insert_continue_right(&mut self,
-                             field_int: FieldInt,
-                             id: Id,
-                             rc_child: RcChild,
-                             is_min: &mut bool,
-                             is_max: &mut bool)
-                             -> Option<RcNode> {
-        self.right
-            .as_mut()
-            .unwrap()
-            .write()
-            .unwrap()
-            .insert(field_int, id, rc_child, is_min, is_max)
+        if Math::gt(value, current) {
+            Ordering::Greater
+        } else if Math::lt(value, current) {
+            Ordering::Less
+        } else {
+            Ordering::Equal
+        }
     }
 
-    fn insert_continue_left(&mut self,
-                            field_int: FieldInt,
-                            id: Id,
-                            rc_child: RcChild,
-                            is_min: &mut bool,
-                            is_max: &mut bool)
-                            -> Option<RcNode> {
-        self.left
-            .as_mut()
-            .unwrap()
-            .write()
-            .unwrap()
-            .insert(field_int, id, rc_child, is_min, is_max)
-    }
+    pub fn find_group(&self, value: &Value) -> Option<Group> {
+        match (&self._type, DataType::from(value)) {
+            (&DataType::Number, DataType::Number) |
+            (&DataType::String, DataType::String) => {}
+            _ => return None,
+        }
 
-    fn set_left(&mut self,
-                field_int: FieldInt,
-                id: Id,
-                rc_child: RcChild,
-                is_min: &mut bool)
-                -> Option<RcNode> {
-        let rc_node = RcNode::new(id, rc_child.clone());
-        let child = rc_child.read()
-            .unwrap();
-        child.item
-            .write()
-            .unwrap()
-            .nodes
-            .insert(field_int, rc_node.clone());
-
-        {
-            let mut node = rc_node.write().unwrap();
-            node.parent = self.self_rc.clone();
-            node.self_rc = Some(rc_node.clone());
-        }
-
-        self.left = Some(rc_node.clone());
-
-        if *is_min { Some(rc_node) } else { None }
+        match self.cmp_value(value) {
+            Ordering::Greater => self.right.as_ref().and_then(|rc_node| rc_node.find_group(value)),
+            Ordering::Less => self.left.as_ref().and_then(|rc_node| rc_node.find_group(value)),
+            Ordering::Equal => Some(self.group.clone()),
+        }
     }
 
-    fn set_right(&mut self,
-                 field_int: FieldInt,
-                 id: Id,
-                 rc_child: RcChild,
-                 is_max: &mut bool)
-                 -> Option<RcNode> {
-        let rc_node = RcNode::new(id, rc_child.clone());
-        let child = rc_child.read().unwrap();
-        child.item
-            .write()
-            .unwrap()
-            .nodes
-            .insert(field_int, rc_node.clone());
-
-        {
-            let mut node = rc_node.write().unwrap();
-            node.parent = self.self_rc.clone();
-            node.self_rc = Some(rc_node.clone());
-        }
-
-        self.right = Some(rc_node.clone());
-
-        if *is_max { Some(rc_node) } else { None }
+    /// Append groups in the range in order, stops once `limit` children are taken.
+    pub fn search(&self, range: &Range, desc: bool, limit: Option<usize>, groups: &mut Groups) {
+        thread_trace!("search from node: {:?}", self.get_value());
+        self.walk(&mut Walk::new(range, desc, limit, groups));
     }
 
-    pub fn search_root(&self, stopped: &AtomicBool, groups: &mut Groups, sub: &mut Sub) {
-        let action_option = self.search_current(stopped, groups, sub);
-        if action_option.is_some() {
-            self.go_right_or_go_left(action_option.unwrap(), stopped, groups, sub);
-        }
-    }
+    /// Number of children between the bounds of the range, excluded values are not counted.
+    pub fn count(&self, range: &Range) -> usize {
+        let value = self.get_value();
 
-    fn search_max_root(&self, stopped: &AtomicBool, groups: &mut Groups, sub: &mut Sub) {
-        let action_option = self.search_max_current(stopped, groups, sub);
-        if action_option.is_some() {
-            self.search_min_or_max_go_right_or_go_left(action_option.unwrap(),
-                                                       stopped,
-                                                       groups,
-                                                       sub);
+        if !range.is_above_lower(&value) {
+            self.right.as_ref().map_or(0, |rc_node| rc_node.count(range))
+        } else if !range.is_below_upper(&value) {
+            self.left.as_ref().map_or(0, |rc_node| rc_node.count(range))
+        } else {
+            count_above(&self.left, range) + self.group.len() + count_below(&self.right, range)
         }
     }
 
-    fn search_min_root(&self, stopped: &AtomicBool, groups: &mut Groups, sub: &mut Sub) {
-        let action_option = self.search_min_current(stopped, groups, sub);
-        if action_option.is_some() {
-            self.search_min_or_max_go_right_or_go_left(action_option.unwrap(),
-                                                       stopped,
-                                                       groups,
-                                                       sub);
+    /// Walk the subtree in order, returns `false` if the walk should not go on.
+    fn walk(&self, walk: &mut Walk) -> bool {
+        if walk.is_full() {
+            return false;
         }
-    }
 
-    pub fn search_max(&self, stopped: &AtomicBool, groups: &mut Groups, sub: &mut Sub) {
-        let action_option = self.search_max_current(stopped, groups, sub);
-        if action_option.is_some() {
-            let action = action_option.unwrap();
+        let value = self.get_value();
+        let above_lower = walk.range.is_above_lower(&value);
+        let below_upper = walk.range.is_below_upper(&value);
 
-            let mut should_stop = true;
+        let (first, first_go, second, second_go) = if walk.desc {
+            (&self.right, below_upper, &self.left, above_lower)
+        } else {
+            (&self.left, above_lower, &self.right, below_upper)
+        };
 
-            if !action.fold_left && action.go_left {
-                if self.left.is_some() {
-                    self.go_left(stopped, groups, sub);
-                }
+        if first_go && first.is_some() && !first.as_ref().unwrap().walk(walk) {
+            return false;
+        }
 
-                if self.parent.is_some() {
-                    should_stop = false;
-                    self.go_top_max(stopped, groups, sub);
-                }
-            }
+        if above_lower && below_upper && walk.range.contains(&value) {
+            thread_trace!("take current childs: {:?}", self.group);
+            walk.take(&self.group);
+        }
 
-            if should_stop {
-                return stop(stopped, sub);
-            }
-        } else if !stopped.load(Ordering::SeqCst) {
-            return stop(stopped, sub);
+        if second_go && second.is_some() && !second.as_ref().unwrap().walk(walk) {
+            return false;
         }
+
+        true
     }
 
-    pub fn search_min(&self, stopped: &AtomicBool, groups: &mut Groups, sub: &mut Sub) {
-        let action_option = self.search_min_current(stopped, groups, sub);
-        if action_option.is_some() {
-            let action = action_option.unwrap();
+    pub fn get_value(&self) -> Value {
+        for child in self.group.values() {
+            return child.read().unwrap().get_value();
+        }
 
-            let mut should_stop = true;
+        unreachable!()
+    }
+}
 
-            if !action.fold_right && action.go_right {
-                if self.right.is_some() {
-                    self.go_right(stopped, groups, sub);
-                }
+impl fmt::Debug for Node {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self.group)
+    }
+}
 
-                if self.parent.is_some() {
-                    should_stop = false;
-                    self.go_top_min(stopped, groups, sub);
-                }
-            }
+/// State of an in order walk, it stops once `limit` children are taken.
+struct Walk<'a> {
+    range: &'a Range,
+    desc: bool,
+    limit: Option<usize>,
+    found: usize,
+    groups: &'a mut Groups,
+}
 
-            if should_stop && !stopped.load(Ordering::SeqCst) {
-                return stop(stopped, sub);
-            }
-        } else if !stopped.load(Ordering::SeqCst) {
-            return stop(stopped, sub);
+impl<'a> Walk<'a> {
+    fn new(range: &'a Range,
+           desc: bool,
+           limit: Option<usize>,
+           groups: &'a mut Groups)
+           -> Walk<'a> {
+        Walk {
+            range: range,
+            desc: desc,
+            limit: limit,
+            found: 0,
+            groups: groups,
         }
     }
 
-    fn go_right(&self, stopped: &AtomicBool, groups: &mut Groups, sub: &mut Sub) {
-        thread_trace!("search min, go right");
-        self.right.as_ref().unwrap().read().unwrap().search_min_root(stopped, groups, sub);
+    fn take(&mut self, group: &Group) {
+        self.found += group.len();
+        self.groups.push(group.clone());
     }
 
-    fn go_left(&self, stopped: &AtomicBool, groups: &mut Groups, sub: &mut Sub) {
-        thread_trace!("search max, go left");
-        self.left.as_ref().unwrap().read().unwrap().search_max_root(stopped, groups, sub);
+    fn is_full(&self) -> bool {
+        self.limit.map_or(false, |limit| self.found >= limit)
     }
+}
 
-    fn go_top_max(&self, stopped: &AtomicBool, groups: &mut Groups, sub: &mut Sub) {
-        thread_trace!("search max, go top");
-        self.parent.as_ref().unwrap().read().unwrap().search_max(stopped, groups, sub);
-    }
+/// Insert the child into the subtree by the value of its data. Returns the new subtree and
+/// whether a node was created for the value, the old subtree is left as it was.
+pub fn insert(rc_node: Option<&RcNode>, id: Id, rc_child: RcChild) -> (RcNode, bool) {
+    let value = rc_child.read().unwrap().get_value();
+    insert_value(rc_node, id, rc_child, &value)
+}
 
-    fn go_top_min(&self, stopped: &AtomicBool, groups: &mut Groups, sub: &mut Sub) {
-        thread_trace!("search min, go top");
-        self.parent.as_ref().unwrap().read().unwrap().search_min(stopped, groups, sub);
+/// The value is read once, data of an item are looked up in the item value.
+fn insert_value(rc_node: Option<&RcNode>,
+                id: Id,
+                rc_child: RcChild,
+                value: &Value)
+                -> (RcNode, bool) {
+    let rc_node = match rc_node {
+        Some(rc_node) => rc_node,
+        None => {
+            thread_trace!("create node");
+            return (RcNode::new(Node::new(id, rc_child)), true);
+        }
+    };
+
+    match rc_node.cmp_value(value) {
+        Ordering::Greater => {
+            thread_trace!("continue insert right");
+            let (right, created) = insert_value(rc_node.right.as_ref(), id, rc_child, value);
+            (balance(rc_node, rc_node.left.clone(), Some(right)), created)
+        }
+        Ordering::Less => {
+            thread_trace!("continue insert left");
+            let (left, created) = insert_value(rc_node.left.as_ref(), id, rc_child, value);
+            (balance(rc_node, Some(left), rc_node.right.clone()), created)
+        }
+        Ordering::Equal => {
+            thread_trace!("found group, insert to current");
+            let mut node = (**rc_node).clone();
+            node.group.insert(id, rc_child);
+            (link(&node, node.left.clone(), node.right.clone()), false)
+        }
     }
+}
 
-    fn search_min_current(&self,
-                          stopped: &AtomicBool,
-                          groups: &mut Groups,
-                          sub: &mut Sub)
-                          -> Option<SearchAction> {
-        if stopped.load(Ordering::SeqCst) {
-            thread_trace!("search min current, other threads had stopped, return none");
-            return None;
-        }
+/// Delete `id` from the group of `value`, the node is unlinked once its group is empty.
+/// Returns the new subtree, `None` if it is empty, and whether a node was unlinked, or `None`
+/// if the id is not found. The old subtree is left as it was.
+pub fn delete(rc_node: &RcNode, id: &str, value: &Value) -> Option<(Option<RcNode>, bool)> {
+    match rc_node.cmp_value(value) {
+        Ordering::Greater => {
+            let (right, unlinked) = delete(rc_node.right.as_ref()?, id, value)?;
+            Some((Some(balance(rc_node, rc_node.left.clone(), right)), unlinked))
+        }
+        Ordering::Less => {
+            let (left, unlinked) = delete(rc_node.left.as_ref()?, id, value)?;
+            Some((Some(balance(rc_node, left, rc_node.right.clone())), unlinked))
+        }
+        Ordering::Equal => {
+            if !rc_node.group.contains_key(id) {
+                return None;
+            }
 
-        let action_option = self.compare_self(&sub.comparisions);
-        if action_option.is_none() {
-            thread_trace!("search min current, action is none, return none");
-            return None;
-        }
+            if rc_node.group.len() > 1 {
+                let mut node = (**rc_node).clone();
+                node.group.remove(id);
+                return Some((Some(link(&node, node.left.clone(), node.right.clone())), false));
+            }
 
-        let action = action_option.unwrap();
-        self.fold_min_action(&action, groups);
+            let subtree = match (rc_node.left.clone(), rc_node.right.clone()) {
+                (Some(left), Some(right)) => {
+                    let (right, successor) = delete_min(&right);
+                    Some(balance(&successor, Some(left), right))
+                }
+                (child, None) | (None, child) => child,
+            };
 
-        if action.is_stopped() {
-            thread_trace!("search min current, action is stopped, return none");
-            return None;
+            Some((subtree, true))
         }
-
-        thread_trace!("search min current, found some action: {:?}", action);
-        Some(action)
     }
+}
 
-    fn search_max_current(&self,
-                          stopped: &AtomicBool,
-                          groups: &mut Groups,
-                          sub: &mut Sub)
-                          -> Option<SearchAction> {
-        if stopped.load(Ordering::SeqCst) {
-            thread_trace!("search min current, other threads had stopped, return none");
-            return None;
+/// Unlink the leftmost node of the subtree, returns the new subtree and the node.
+fn delete_min(rc_node: &RcNode) -> (Option<RcNode>, RcNode) {
+    match rc_node.left {
+        Some(ref left) => {
+            let (left, min) = delete_min(left);
+            (Some(balance(rc_node, left, rc_node.right.clone())), min)
         }
+        None => (rc_node.right.clone(), rc_node.clone()),
+    }
+}
 
-        let action_option = self.compare_self(&sub.comparisions);
-        if action_option.is_none() {
-            thread_trace!("search min current, action is none, return none");
-            return None;
-        }
+/// Copy of the node over the children, rotated if their heights differ by more than one.
+fn balance(node: &Node, left: Option<RcNode>, right: Option<RcNode>) -> RcNode {
+    let difference = get_height(&left) as isize - get_height(&right) as isize;
 
-        let action = action_option.unwrap();
-        self.fold_max_action(&action, groups);
+    if difference > 1 {
+        let rc_left = left.unwrap();
 
-        if action.is_stopped() {
-            thread_trace!("search min current, action is stopped, return none");
-            return None;
+        if get_height(&rc_left.left) < get_height(&rc_left.right) {
+            let rc_middle = rc_left.right.clone().unwrap();
+            let left = link(&rc_left, rc_left.left.clone(), rc_middle.left.clone());
+            let right = link(node, rc_middle.right.clone(), right);
+            link(&rc_middle, Some(left), Some(right))
+        } else {
+            let right = link(node, rc_left.right.clone(), right);
+            link(&rc_left, rc_left.left.clone(), Some(right))
         }
+    } else if difference < -1 {
+        let rc_right = right.unwrap();
 
-        thread_trace!("search min current, found some action: {:?}", action);
-        Some(action)
+        if get_height(&rc_right.right) < get_height(&rc_right.left) {
+            let rc_middle = rc_right.left.clone().unwrap();
+            let left = link(node, left, rc_middle.left.clone());
+            let right = link(&rc_right, rc_middle.right.clone(), rc_right.right.clone());
+            link(&rc_middle, Some(left), Some(right))
+        } else {
+            let left = link(node, left, rc_right.left.clone());
+            link(&rc_right, Some(left), rc_right.right.clone())
+        }
+    } else {
+        link(node, left, right)
     }
+}
 
-    fn search_current(&self,
-                      stopped: &AtomicBool,
-                      groups: &mut Groups,
-                      sub: &mut Sub)
-                      -> Option<SearchAction> {
-        if stopped.load(Ordering::SeqCst) {
-            thread_trace!("other threads had stopped, stopping current.");
-            return None;
-        }
+/// Copy of the node over the children, the height and the size are computed from them.
+fn link(node: &Node, left: Option<RcNode>, right: Option<RcNode>) -> RcNode {
+    RcNode::new(Node {
+        _type: node._type.clone(),
+        group: node.group.clone(),
+        height: 1 + cmp::max(get_height(&left), get_height(&right)),
+        size: node.group.len() + get_size(&left) + get_size(&right),
+        left: left,
+        right: right,
+    })
+}
 
-        let action_option = self.compare_self(&sub.comparisions);
-        if action_option.is_none() {
-            stop(stopped, sub);
-            thread_trace!("action is none, stopping sub.");
-            return None;
-        }
+fn get_height(rc_node: &Option<RcNode>) -> usize {
+    rc_node.as_ref().map_or(0, |rc_node| rc_node.height)
+}
 
-        let action = action_option.unwrap();
-        self.fold_action(&action, groups);
+fn get_size(rc_node: &Option<RcNode>) -> usize {
+    rc_node.as_ref().map_or(0, |rc_node| rc_node.size)
+}
 
-        if action.is_stopped() {
-            stop(stopped, sub);
-            thread_trace!("action is stopped, stopping sub.");
-            return None;
-        }
+/// Children above the lower bound, the subtree is below the upper bound.
+fn count_above(rc_node: &Option<RcNode>, range: &Range) -> usize {
+    let node = match *rc_node {
+        Some(ref rc_node) => rc_node,
+        None => return 0,
+    };
 
-        thread_trace!("found some action: {:?}", action);
-        Some(action)
+    if range.is_above_lower(&node.get_value()) {
+        count_above(&node.left, range) + node.group.len() + get_size(&node.right)
+    } else {
+        count_above(&node.right, range)
     }
+}
 
-    fn search_min_or_max_go_right_or_go_left(&self,
-                                             action: SearchAction,
-                                             stopped: &AtomicBool,
-                                             groups: &mut Groups,
-                                             sub: &mut Sub) {
-        if action.go_right && self.right.is_some() {
-            self.right
-                .as_ref()
-                .unwrap()
-                .read()
-                .unwrap()
-                .search_min_root(stopped, groups, sub);
-        }
-
-        if action.go_left && self.left.is_some() {
-            self.left
-                .as_ref()
-                .unwrap()
-                .read()
-                .unwrap()
-                .search_min_root(stopped, groups, sub);
+/// Children below the upper bound, the subtree is above the lower bound.
+fn count_below(rc_node: &Option<RcNode>, range: &Range) -> usize {
+    let node = match *rc_node {
+        Some(ref rc_node) => rc_node,
+        None => return 0,
+    };
 
-        }
+    if range.is_below_upper(&node.get_value()) {
+        get_size(&node.left) + node.group.len() + count_below(&node.right, range)
+    } else {
+        count_below(&node.left, range)
     }
+}
 
-    fn go_right_or_go_left(&self,
-                           action: SearchAction,
-                           stopped: &AtomicBool,
-                           groups: &mut Groups,
-                           sub: &mut Sub) {
-        let mut no_right_go = true;
-        let mut no_left_go = true;
-
-        if action.go_right && self.right.is_some() {
-            no_right_go = false;
-            self.right
-                .as_ref()
-                .unwrap()
-                .read()
-                .unwrap()
-                .search_root(stopped, groups, sub);
-        }
 
-        if action.go_left && self.left.is_some() {
-            no_left_go = false;
-            self.left
-                .as_ref()
-                .unwrap()
-                .read()
-                .unwrap()
-                .search_root(stopped, groups, sub);
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, HashMap, HashSet};
+    use serde_json::{Map, Value};
+    use to_value;
+    use super::{Node, insert, delete};
+    use super::super::rc::{RcNode, RcItem, RcData, RcChild};
 
-        }
+    const FIELD: &'static str = "1";
 
-        if no_left_go && no_right_go {
-            return stop(stopped, sub);
-        }
+    /// Index of one field, the value of every id is kept to find its node, like `Deleter`
+    /// reads it from the item.
+    #[derive(Default, Clone)]
+    struct Index {
+        root: Option<RcNode>,
+        values: HashMap<String, usize>,
     }
 
-    fn fold_min_action(&self, action: &SearchAction, groups: &mut Groups) {
-        if action.take {
-            thread_trace!("fold min action, take current childs: {:?}",
-                          &*self.group.read().unwrap());
+    impl Index {
+        fn insert(&mut self, id: &str, value: usize) {
+            let mut object = Map::new();
+            object.insert(FIELD.to_owned(), to_value(value));
+            let mut item_value = Map::new();
+            item_value.insert("item".to_owned(), Value::Object(object));
 
-            self.append_to(groups);
-        }
+            let rc_item = RcItem::new(id.to_owned(),
+                                      Value::Object(item_value),
+                                      &[],
+                                      &HashMap::new());
+            let rc_child = RcChild::new(RcData::new(to_value(value)), rc_item);
+            self.values.insert(id.to_owned(), value);
 
-        if action.fold_right {
-            thread_trace!("fold min action, fold right");
-            self.fold_right_top(groups);
+            let (root, _) = insert(self.root.as_ref(), id.to_owned(), rc_child);
+            self.root = Some(root);
         }
-    }
 
-    fn fold_max_action(&self, action: &SearchAction, groups: &mut Groups) {
-        if action.take {
-            thread_trace!("fold max action, take current childs: {:?}",
-                          &*self.group.read().unwrap());
-
-            self.append_to(groups);
+        fn delete(&mut self, id: &str) {
+            let value = self.values.remove(id).unwrap();
+            let root = self.root.take().unwrap();
+            self.root = delete(&root, id, &to_value(value)).unwrap().0;
         }
 
-        if action.fold_left {
-            thread_trace!("fold max action, fold left");
-            self.fold_left_top(groups);
-        }
-    }
+        /// Check the heights, balance and sizes, returns the values in order.
+        fn check(&self) -> Vec<(usize, usize)> {
+            let mut values = Vec::new();
 
-    fn fold_action(&self, action: &SearchAction, groups: &mut Groups) {
-        if action.take {
-            thread_trace!("fold action, take current childs: {:?}",
-                          &*self.group.read().unwrap());
+            if let Some(ref root) = self.root {
+                check_node(root, &mut values);
+            }
 
-            self.append_to(groups);
-        }
+            for window in values.windows(2) {
+                assert!(window[0].0 < window[1].0, "not in order: {:?}", values);
+            }
 
-        if action.fold_right {
-            thread_trace!("fold right");
-            self.fold_right_to(groups);
+            values
         }
 
-        if action.fold_left {
-            thread_trace!("fold left");
-            self.fold_left_to(groups);
+        fn get_height(&self) -> usize {
+            self.root.as_ref().map_or(0, |root| root.height)
         }
-    }
 
-    fn fold_left_top(&self, groups: &mut Groups) {
-        self.fold_left_to(groups);
-
-        if self.parent.is_some() {
-            self.parent.as_ref().unwrap().read().unwrap().fold_current_left(groups);
+        fn get_size(&self) -> usize {
+            self.root.as_ref().map_or(0, |root| root.size)
         }
     }
 
-    fn fold_right_top(&self, groups: &mut Groups) {
-        self.fold_right_to(groups);
+    /// Values and group sizes of the subtree in order, returns its height and size.
+    fn check_node(node: &RcNode, values: &mut Vec<(usize, usize)>) -> (usize, usize) {
+        let mut heights = [0, 0];
+        let mut size = node.group.len();
+
+        for (index, child) in [&node.left, &node.right].iter().enumerate() {
+            if let Some(ref rc_child) = **child {
+                if index == 1 {
+                    values.push((get_usize(&node.get_value()), node.group.len()));
+                }
 
-        if self.parent.is_some() {
-            self.parent.as_ref().unwrap().read().unwrap().fold_current_right(groups);
+                let (height, child_size) = check_node(rc_child, values);
+                heights[index] = height;
+                size += child_size;
+            } else if index == 1 {
+                values.push((get_usize(&node.get_value()), node.group.len()));
+            }
         }
-    }
 
-    fn fold_current_left(&self, groups: &mut Groups) {
-        self.append_to(groups);
-        self.fold_left_to(groups);
-    }
+        assert!((heights[0] as isize - heights[1] as isize).abs() <= 1,
+                "unbalanced at {:?}, heights: {:?}",
+                node.get_value(),
+                heights);
+        assert_eq!(node.height, 1 + heights[0].max(heights[1]));
+        assert_eq!(node.size, size);
 
-    fn fold_current_right(&self, groups: &mut Groups) {
-        self.append_to(groups);
-        self.fold_right_to(groups);
+        (node.height, node.size)
     }
 
-    fn fold_left_to(&self, groups: &mut Groups) {
-        if self.left.is_some() {
-            self.left.as_ref().unwrap().read().unwrap().fold_to(groups);
-        }
-    }
+    /// Nodes of the subtree, by their address.
+    fn collect_nodes(rc_node: &RcNode, nodes: &mut HashSet<*const Node>) {
+        nodes.insert(&**rc_node as *const Node);
 
-    fn fold_right_to(&self, groups: &mut Groups) {
-        if self.right.is_some() {
-            self.right.as_ref().unwrap().read().unwrap().fold_to(groups);
+        for child in rc_node.left.iter().chain(rc_node.right.iter()) {
+            collect_nodes(child, nodes);
         }
     }
 
-    fn fold_to(&self, groups: &mut Groups) {
-        self.append_to(groups);
-        self.fold_right_to(groups);
-        self.fold_left_to(groups);
+    fn get_usize(value: &Value) -> usize {
+        value.as_u64().unwrap() as usize
     }
 
-    fn append_to(&self, groups: &mut Groups) {
-        groups.push(self.group.clone());
+    /// Largest height of an AVL tree with `count` nodes.
+    fn get_max_height(count: usize) -> usize {
+        (1.45 * ((count + 2) as f64).log2()) as usize
     }
 
-    fn compare_self(&self, comparisions: &[Comparision]) -> Option<SearchAction> {
-        let mut prev_action = None;
-
-        for comparision in comparisions {
-            let compared_action = comparision.compare(&self.get_value());
-            if compared_action.is_none() {
-                return None;
-            }
+    #[test]
+    fn test_sorted_insert() {
+        let mut index = Index::default();
 
-            if prev_action.is_none() {
-                prev_action = compared_action;
-            } else {
-                let merged_action = prev_action.as_ref().unwrap().merge(&compared_action.unwrap());
-                if merged_action.is_none() {
-                    return None;
-                } else {
-                    prev_action = merged_action;
-                }
-            }
+        for value in 0..1000 {
+            index.insert(&value.to_string(), value);
         }
 
-        prev_action
-    }
+        let values = index.check();
+        assert_eq!(values, (0..1000).map(|value| (value, 1)).collect::<Vec<_>>());
+        assert_eq!(index.get_size(), 1000);
+        assert!(index.get_height() <= get_max_height(1000));
 
-    pub fn delete(&mut self, id: &str) -> (bool, Option<RcNode>, Option<RcNode>) {
-        if self.group.read().unwrap().len() == 1 {
-            let (clear, root) = self.delete_self();
-            (clear, self.self_rc.take(), root)
-        } else {
-            let rc_child = self.group.write().unwrap().remove(id);
-            if rc_child.is_some() {
-                rc_child.unwrap().destroy();
-            }
+        let mut index = Index::default();
 
-            (false, None, None)
+        for value in (0..1000).rev() {
+            index.insert(&value.to_string(), value);
         }
-    }
 
-    fn is_root(&self) -> bool {
-        self.parent.is_none()
+        assert_eq!(index.check().len(), 1000);
+        assert!(index.get_height() <= get_max_height(1000));
     }
 
-    fn delete_self(&mut self) -> (bool, Option<RcNode>) {
-        let mut clear = false;
-        let mut root = None;
+    #[test]
+    fn test_copy_path() {
+        let mut index = Index::default();
 
-        if self.has_no_child_node() {
-            if self.is_root() {
-                clear = true;
-            } else {
-                self.parent.as_ref().unwrap().write().unwrap().delete_child_node(&self.self_rc);
-            }
-        } else if self.has_two_child_node() {
-            let is_root = self.is_root();
-            let need_link_rc_node = self.take_right_min_node();
+        for value in 0..1000 {
+            index.insert(&value.to_string(), value % 500);
+        }
 
-            {
-                let mut need_link_node = need_link_rc_node.write().unwrap();
+        let old = index.clone();
+        index.insert("1000", 1000);
+        index.delete("250");
+        index.delete("750");
 
-                self.replace_parent_child_node(need_link_rc_node.clone());
+        // The old version is left as it was, the new one shares all but the changed paths.
+        assert_eq!(old.check(), (0..500).map(|value| (value, 2)).collect::<Vec<_>>());
+        assert_eq!(old.get_size(), 1000);
 
-                if is_root {
-                    need_link_node.parent = None;
-                } else {
-                    need_link_node.parent = Some(self.parent.take().unwrap());
-                }
+        let mut expected = (0..500).map(|value| (value, 2)).collect::<Vec<_>>();
+        expected.remove(250);
+        expected.push((1000, 1));
+        assert_eq!(index.check(), expected);
+        assert_eq!(index.get_size(), 999);
 
-                let left_rc_node = self.left.take().unwrap();
-                left_rc_node.write().unwrap().parent = Some(need_link_rc_node.clone());
-                need_link_node.left = Some(left_rc_node);
+        let mut old_nodes = HashSet::new();
+        let mut nodes = HashSet::new();
+        collect_nodes(old.root.as_ref().unwrap(), &mut old_nodes);
+        collect_nodes(index.root.as_ref().unwrap(), &mut nodes);
 
-                if self.right.is_some() {
-                    let right_rc_node = self.right.take().unwrap();
-                    right_rc_node.write().unwrap().parent = Some(need_link_rc_node.clone());
-                    need_link_node.right = Some(right_rc_node);
-                }
-            }
+        let copied = nodes.difference(&old_nodes).count();
+        assert!(copied <= 3 * get_max_height(500), "copied nodes: {}", copied);
+    }
 
-            if is_root {
-                clear = true;
-                root = Some(need_link_rc_node);
-            }
-        } else if self.left.is_some() {
-            let rc_node = self.left.take().unwrap();
+    #[test]
+    fn test_group() {
+        let mut index = Index::default();
 
-            if self.is_root() {
-                rc_node.write().unwrap().parent = None;
-                clear = true;
-                root = Some(rc_node);
-            } else {
-                rc_node.write().unwrap().parent = self.parent.clone();
-                self.replace_parent_child_node(rc_node);
-            }
-        } else if self.right.is_some() {
-            let rc_node = self.right.take().unwrap();
+        for id in 0..30 {
+            index.insert(&id.to_string(), id % 3);
+        }
 
-            if self.is_root() {
-                rc_node.write().unwrap().parent = None;
-                clear = true;
-                root = Some(rc_node);
-            } else {
-                rc_node.write().unwrap().parent = self.parent.clone();
-                self.replace_parent_child_node(rc_node);
-            }
-        } else {
-            unreachable!()
+        assert_eq!(index.check(), vec![(0, 10), (1, 10), (2, 10)]);
+        assert_eq!(index.get_size(), 30);
+
+        for id in 0..9 {
+            index.delete(&(id * 3 + 1).to_string());
         }
 
-        (clear, root)
-    }
+        assert_eq!(index.check(), vec![(0, 10), (1, 1), (2, 10)]);
+        assert_eq!(index.get_size(), 21);
 
-    fn right_child_no_left(&self) -> bool {
-        self.right.as_ref().unwrap().read().unwrap().left.is_none()
+        index.delete("28");
+        assert_eq!(index.check(), vec![(0, 10), (2, 10)]);
+        assert_eq!(index.get_size(), 20);
     }
 
-    fn left_child_no_left(&self) -> bool {
-        self.left.as_ref().unwrap().read().unwrap().left.is_none()
-    }
+    #[test]
+    fn test_delete_two_children() {
+        let mut index = Index::default();
 
-    fn take_left_min_node(&mut self) -> RcNode {
-        if self.left_child_no_left() {
-            self.left.take().unwrap()
-        } else {
-            self.left.as_ref().unwrap().write().unwrap().take_left_min_node()
+        for value in 0..200 {
+            index.insert(&value.to_string(), value);
         }
-    }
 
-    fn take_right_min_node(&mut self) -> RcNode {
-        if self.right_child_no_left() {
-            self.right.take().unwrap()
-        } else {
-            self.right.as_ref().unwrap().write().unwrap().take_left_min_node()
-        }
-    }
+        let mut expected = (0..200).collect::<Vec<_>>();
 
-    fn replace_parent_child_node(&self, rc_node: RcNode) {
-        let mut parent = self.parent.as_ref().unwrap().write().unwrap();
-        parent.replace_child_node(&self.self_rc, rc_node);
-    }
+        while index.get_size() > 2 {
+            let (value, has_two_children) = {
+                let root = index.root.as_ref().unwrap();
+                (get_usize(&root.get_value()), root.left.is_some() && root.right.is_some())
+            };
+            assert!(has_two_children);
 
-    fn replace_child_node(&mut self, from: &Option<RcNode>, to: RcNode) {
-        if self.left == *from {
-            self.left = Some(to);
-        } else {
-            self.right = Some(to);
-        }
-    }
+            index.delete(&value.to_string());
+            expected.retain(|&other| other != value);
 
-    fn delete_child_node(&mut self, self_rc: &Option<RcNode>) {
-        if self.left == *self_rc {
-            self.left = None;
-        } else {
-            self.right = None;
+            let values = index.check();
+            assert_eq!(values.iter().map(|&(value, _)| value).collect::<Vec<_>>(), expected);
+            assert!(index.get_height() <= get_max_height(expected.len()));
         }
     }
 
-    fn has_no_child_node(&self) -> bool {
-        self.left.is_none() && self.right.is_none()
-    }
+    #[test]
+    fn test_interleaved() {
+        let mut index = Index::default();
+        let mut expected = BTreeMap::new();
+        let mut ids = Vec::new();
+        let mut seed = 7usize;
 
-    fn has_two_child_node(&self) -> bool {
-        self.left.is_some() && self.right.is_some()
-    }
+        for round in 0..3000 {
+            seed = seed.wrapping_mul(1103515245).wrapping_add(12345) % 2147483648;
 
-    pub fn get_value(&self) -> Value {
-        for child in self.group.read().unwrap().values() {
-            return child.read().unwrap().get_value();
+            if ids.is_empty() || seed % 3 != 0 {
+                let id = round.to_string();
+                let value = seed % 500;
+                index.insert(&id, value);
+                *expected.entry(value).or_insert(0) += 1;
+                ids.push((id, value));
+            } else {
+                let (id, value) = ids.swap_remove(seed % ids.len());
+                index.delete(&id);
+
+                let is_empty = {
+                    let count = expected.get_mut(&value).unwrap();
+                    *count -= 1;
+                    *count == 0
+                };
+
+                if is_empty {
+                    expected.remove(&value);
+                }
+            }
+
+            if round % 100 == 0 {
+                assert_eq!(index.check(), expected.clone().into_iter().collect::<Vec<_>>());
+                assert_eq!(index.get_size(), ids.len());
+                assert!(index.get_height() <= get_max_height(expected.len()));
+            }
         }
 
-        unreachable!()
-    }
-}
+        while let Some((id, _)) = ids.pop() {
+            index.delete(&id);
+        }
 
-impl fmt::Debug for Node {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{:?}", self.group)
+        assert!(index.root.is_none());
     }
 }
-
-#[inline]
-fn stop(stopped: &AtomicBool, sub: &mut Sub) {
-    sub.stopped = true;
-    stopped.store(true, Ordering::SeqCst);
-}
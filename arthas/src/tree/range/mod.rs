@@ -0,0 +1,157 @@
+use std::cmp::Ordering;
+use serde_json::Value;
+use super::cmp::Cmp;
+use super::math::Math;
+use super::comparision::Comparision;
+
+
+#[derive(Debug, Clone)]
+pub struct Bound {
+    pub value: Value,
+    pub inclusive: bool,
+}
+
+impl Bound {
+    pub fn new(value: Value, inclusive: bool) -> Bound {
+        Bound {
+            value: value,
+            inclusive: inclusive,
+        }
+    }
+
+    pub fn is_above(&self, value: &Value) -> bool {
+        match cmp_bound(value, &self.value) {
+            Ordering::Greater => true,
+            Ordering::Equal => self.inclusive,
+            Ordering::Less => false,
+        }
+    }
+
+    pub fn is_below(&self, value: &Value) -> bool {
+        match cmp_bound(value, &self.value) {
+            Ordering::Less => true,
+            Ordering::Equal => self.inclusive,
+            Ordering::Greater => false,
+        }
+    }
+}
+
+
+/// Values between the bounds, except the excluded ones.
+#[derive(Debug, Default, Clone)]
+pub struct Range {
+    pub lower: Option<Bound>,
+    pub upper: Option<Bound>,
+    pub excluded: Vec<Value>,
+}
+
+impl Range {
+    pub fn new(comparisions: &[Comparision]) -> Range {
+        let mut range = Range { ..Default::default() };
+
+        for comparision in comparisions {
+            let value = &comparision.other;
+
+            match comparision.cmp {
+                Cmp::Eq => {
+                    range.set_lower(value, true);
+                    range.set_upper(value, true);
+                }
+                Cmp::Gt => range.set_lower(value, false),
+                Cmp::Ge => range.set_lower(value, true),
+                Cmp::Lt => range.set_upper(value, false),
+                Cmp::Le => range.set_upper(value, true),
+                Cmp::Ne => range.excluded.push(value.clone()),
+                Cmp::Ieq => {}
+            }
+        }
+
+        range
+    }
+
+    pub fn between(lower: Bound, upper: Bound) -> Range {
+        Range {
+            lower: Some(lower),
+            upper: Some(upper),
+            excluded: Vec::new(),
+        }
+    }
+
+    pub fn is_above_lower(&self, value: &Value) -> bool {
+        self.lower.as_ref().map_or(true, |lower| lower.is_above(value))
+    }
+
+    pub fn is_below_upper(&self, value: &Value) -> bool {
+        self.upper.as_ref().map_or(true, |upper| upper.is_below(value))
+    }
+
+    /// Only one value is in the range.
+    pub fn is_single(&self) -> bool {
+        match (&self.lower, &self.upper) {
+            (&Some(ref lower), &Some(ref upper)) => {
+                lower.inclusive && upper.inclusive &&
+                cmp_bound(&lower.value, &upper.value) == Ordering::Equal
+            }
+            _ => false,
+        }
+    }
+
+    pub fn contains(&self, value: &Value) -> bool {
+        self.is_above_lower(value) && self.is_below_upper(value) &&
+        !self.excluded.iter().any(|excluded| Math::eq(value, excluded))
+    }
+
+    fn set_lower(&mut self, value: &Value, inclusive: bool) {
+        let tighter = match self.lower {
+            Some(ref lower) => {
+                match cmp_bound(value, &lower.value) {
+                    Ordering::Greater => true,
+                    Ordering::Equal => !inclusive,
+                    Ordering::Less => false,
+                }
+            }
+            None => true,
+        };
+
+        if tighter {
+            self.lower = Some(Bound::new(value.clone(), inclusive));
+        }
+    }
+
+    fn set_upper(&mut self, value: &Value, inclusive: bool) {
+        let tighter = match self.upper {
+            Some(ref upper) => {
+                match cmp_bound(value, &upper.value) {
+                    Ordering::Less => true,
+                    Ordering::Equal => !inclusive,
+                    Ordering::Greater => false,
+                }
+            }
+            None => true,
+        };
+
+        if tighter {
+            self.upper = Some(Bound::new(value.clone(), inclusive));
+        }
+    }
+}
+
+/// Tuples are compared on the bound's fields only, so a shorter bound is a prefix.
+fn cmp_bound(value: &Value, bound: &Value) -> Ordering {
+    if value.is_array() && bound.is_array() {
+        for (value, bound) in value.as_array().unwrap().iter().zip(bound.as_array().unwrap()) {
+            let ordering = cmp_bound(value, bound);
+            if ordering != Ordering::Equal {
+                return ordering;
+            }
+        }
+
+        Ordering::Equal
+    } else if Math::lt(value, bound) {
+        Ordering::Less
+    } else if Math::gt(value, bound) {
+        Ordering::Greater
+    } else {
+        Ordering::Equal
+    }
+}
@@ -1,55 +1,49 @@
-
-use std::sync::RwLock;
+use std::sync::{Arc, RwLock};
 use std::ops::Deref;
-use std::ops::DerefMut;
 use serde_json::Value;
 use super::DataType;
 
 
 #[derive(Clone, Debug)]
-pub struct RcData(*mut RwLock<Data>);
+pub struct RcData(Arc<RwLock<Data>>);
 
 impl RcData {
-    pub fn new(value: *const Value) -> RcData {
-        RcData(Box::into_raw(Box::new(RwLock::new(Data::new(value)))))
+    pub fn new(value: Value) -> RcData {
+        RcData(Arc::new(RwLock::new(Data::new(Source::Value(value)))))
     }
 
-    pub fn destroy(self) {
-        unsafe {
-            Box::from_raw(self.0);
-        }
+    pub fn from_item(item_value: Arc<Value>, field_int: Arc<str>) -> RcData {
+        RcData(Arc::new(RwLock::new(Data::new(Source::Item(item_value, field_int)))))
     }
 }
 
-unsafe impl Send for RcData {}
-unsafe impl Sync for RcData {}
-
 impl Deref for RcData {
     type Target = RwLock<Data>;
     fn deref(&self) -> &Self::Target {
-        unsafe { &*self.0 }
+        &self.0
     }
 }
 
-impl DerefMut for RcData {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        unsafe { &mut *self.0 }
-    }
-}
 
+/// Field values are read from the item value, derived values are owned.
+#[derive(Clone, Debug)]
+enum Source {
+    Item(Arc<Value>, Arc<str>),
+    Value(Value),
+}
 
 #[derive(Clone, Debug)]
 pub struct Data {
-    pub value: *const Value,
+    source: Source,
     pub _type: DataType,
 }
 
 impl Data {
-    pub fn new(value: *const Value) -> Data {
-        let _type = DataType::from(unsafe { &*value });
+    fn new(source: Source) -> Data {
+        let _type = DataType::from(get_source_value(&source));
 
         Data {
-            value: value,
+            source: source,
             _type: _type,
         }
     }
@@ -62,6 +56,100 @@ impl Data {
     }
 
     pub fn get_value(&self) -> &Value {
-        unsafe { &*self.value }
+        get_source_value(&self.source)
+    }
+}
+
+#[inline]
+fn get_source_value(source: &Source) -> &Value {
+    match *source {
+        Source::Item(ref item_value, ref field_int) => &item_value["item"][&**field_int],
+        Source::Value(ref value) => value,
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use serde_json::{Map, Value};
+    use to_value;
+    use super::{RcData, Source};
+    use super::super::DataType;
+
+    fn create_item_value() -> Arc<Value> {
+        let mut object = Map::new();
+        object.insert("1".to_owned(), to_value("Hello world!"));
+        object.insert("2".to_owned(), to_value(10));
+        object.insert("3".to_owned(), to_value(vec![1, 2]));
+
+        let mut item_value = Map::new();
+        item_value.insert("item".to_owned(), Value::Object(object));
+        Arc::new(Value::Object(item_value))
+    }
+
+    #[test]
+    fn test_item_source() {
+        let item_value = create_item_value();
+        let string = RcData::from_item(item_value.clone(), Arc::from("1"));
+        let number = RcData::from_item(item_value.clone(), Arc::from("2"));
+        let array = RcData::from_item(item_value.clone(), Arc::from("3"));
+
+        assert_eq!(Arc::strong_count(&item_value), 4);
+        assert_eq!(string.read().unwrap().get_value(), &to_value("Hello world!"));
+        assert_eq!(number.read().unwrap().get_value(), &to_value(10));
+        assert_eq!(array.read().unwrap().get_value(), &to_value(vec![1, 2]));
+
+        let data = string.read().unwrap();
+        assert!(data.can_index());
+
+        match data.source {
+            Source::Item(ref value, _) => assert!(Arc::ptr_eq(value, &item_value)),
+            Source::Value(_) => panic!("field value is copied"),
+        }
+
+        let array = array.read().unwrap();
+        match array._type {
+            DataType::Array => assert!(!array.can_index()),
+            ref _type => panic!("array has type {:?}", _type),
+        }
+    }
+
+    #[test]
+    fn test_missing_field() {
+        let item_value = create_item_value();
+        let data = RcData::from_item(item_value, Arc::from("4"));
+
+        assert_eq!(data.read().unwrap().get_value(), &Value::Null);
+        assert!(!data.read().unwrap().can_index());
+    }
+
+    #[test]
+    fn test_value_source() {
+        let data = RcData::new(to_value("hello world!"));
+
+        let data = data.read().unwrap();
+        assert_eq!(data.get_value(), &to_value("hello world!"));
+        assert!(data.can_index());
+
+        match data.source {
+            Source::Value(_) => (),
+            Source::Item(..) => panic!("derived value is read from an item"),
+        }
+    }
+
+    #[test]
+    fn test_release() {
+        let item_value = create_item_value();
+        let weak = Arc::downgrade(&item_value);
+        let datas = vec![RcData::from_item(item_value.clone(), Arc::from("1")),
+                         RcData::from_item(item_value, Arc::from("2"))];
+        let cloned = datas[0].clone();
+
+        drop(datas);
+        assert!(weak.upgrade().is_some());
+
+        drop(cloned);
+        assert!(weak.upgrade().is_none());
     }
 }
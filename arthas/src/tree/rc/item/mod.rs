@@ -1,84 +1,78 @@
 
-use std::sync::RwLock;
+use std::sync::{Arc, RwLock};
 use std::ops::Deref;
-use std::ops::DerefMut;
 use item::{Id, FieldInt, get_len_field_int};
 use serde_json::Value;
 use std::collections::HashMap;
 use super::RcData;
-use super::RcNode;
 use traits::get_unique_int_str;
 use super::super::len::Len;
+use super::super::compound::Compound;
+use super::super::collation::Collations;
 
 
 lazy_static! {
     static ref _ID_INT_STR: String = get_unique_int_str("_id");
+    static ref FIELD_INTS: RwLock<HashMap<FieldInt, Arc<str>>> = RwLock::new(HashMap::new());
 }
 
 
 #[derive(Clone, Debug)]
-pub struct RcItem(*mut RwLock<Item>);
+pub struct RcItem(Arc<RwLock<Item>>);
 
 impl RcItem {
-    pub fn new(id: Id, value: Value) -> RcItem {
-        RcItem(Box::into_raw(Box::new(RwLock::new(Item::new(id, value)))))
-    }
-
-    pub fn destroy(self) {
-        let rc_item = unsafe { Box::from_raw(self.0) };
-        let mut item = rc_item.write().unwrap();
-        item.nodes.clear();
-
-        for (_, rc_data) in item.datas.drain() {
-            rc_data.destroy();
-        }
+    pub fn new(id: Id, value: Value, compounds: &[Compound], collations: &Collations) -> RcItem {
+        RcItem(Arc::new(RwLock::new(Item::new(id, value, compounds, collations))))
     }
 }
 
-unsafe impl Send for RcItem {}
-unsafe impl Sync for RcItem {}
-
 impl Deref for RcItem {
     type Target = RwLock<Item>;
     fn deref(&self) -> &Self::Target {
-        unsafe { &*self.0 }
-    }
-}
-
-impl DerefMut for RcItem {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        unsafe { &mut *self.0 }
+        &self.0
     }
 }
 
 
+/// Items are shared by the versions of a tree and never changed once created.
 #[derive(Clone, Debug)]
 pub struct Item {
     pub id: Id,
-    pub nodes: HashMap<FieldInt, RcNode>,
     pub datas: HashMap<FieldInt, RcData>,
-    pub value: Value,
+    pub value: Arc<Value>,
 }
 
 impl Item {
-    pub fn new(id: Id, value: Value) -> Item {
+    pub fn new(id: Id, value: Value, compounds: &[Compound], collations: &Collations) -> Item {
         if value.is_object() {
             let mut datas = HashMap::new();
+            let value = Arc::new(value);
+            let object = value.get("item").unwrap().as_object().unwrap();
 
-            for (field_int, value) in value.get("item").unwrap().as_object().unwrap() {
+            for (field_int, field_value) in object {
                 if field_int != &*_ID_INT_STR {
-                    datas.insert(field_int.to_owned(), RcData::new(value as *const Value));
+                    let rc_data = match collations.get(field_int) {
+                        Some(collation) => RcData::new(collation.collate(field_value)),
+                        None => RcData::from_item(value.clone(), get_shared_field_int(field_int)),
+                    };
+
+                    datas.insert(field_int.to_owned(), rc_data);
 
-                    if value.can_len() {
+                    if field_value.can_len() {
                         datas.insert(get_len_field_int(field_int),
-                                     value.create_len_rc_data().unwrap());
+                                     field_value.create_len_rc_data().unwrap());
                     }
                 }
             }
 
+            for compound in compounds {
+                if let Some(rc_data) = compound.create_rc_data(object, collations) {
+                    datas.insert(compound.field_int.clone(), rc_data);
+                }
+            }
+
             Item {
                 id: id,
-                nodes: HashMap::new(),
                 datas: datas,
                 value: value,
             }
@@ -87,7 +81,83 @@ impl Item {
         }
     }
 
-    pub fn get_pointer(&self) -> *const Value {
-        &self.value as *const Value
+    pub fn get_value(&self) -> Arc<Value> {
+        self.value.clone()
+    }
+}
+
+/// Field ints are shared by the datas of all items, instead of one copy per data.
+fn get_shared_field_int(field_int: &str) -> Arc<str> {
+    if let Some(shared) = FIELD_INTS.read().unwrap().get(field_int) {
+        return shared.clone();
+    }
+
+    FIELD_INTS.write()
+        .unwrap()
+        .entry(field_int.to_owned())
+        .or_insert_with(|| Arc::from(field_int))
+        .clone()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::collections::HashMap;
+    use serde_json::{Map, Value};
+    use to_value;
+    use item::get_len_field_int;
+    use traits::get_unique_int_str;
+    use super::{RcItem, get_shared_field_int};
+    use super::super::super::compound::Compound;
+    use super::super::super::collation::Collation;
+
+    fn create_value() -> Value {
+        let mut object = Map::new();
+        object.insert(get_unique_int_str("_id"), to_value("id"));
+        object.insert(get_unique_int_str("title"), to_value("Hello World!"));
+        object.insert(get_unique_int_str("views"), to_value(10));
+
+        let mut value = Map::new();
+        value.insert("item".to_owned(), Value::Object(object));
+        Value::Object(value)
+    }
+
+    #[test]
+    fn test_datas() {
+        let title = get_unique_int_str("title");
+        let views = get_unique_int_str("views");
+        let compound = Compound::new(&["title".to_owned(), "views".to_owned()]);
+        let mut collations = HashMap::new();
+        collations.insert(title.clone(), Collation::NoCase);
+
+        let rc_item = RcItem::new("id".to_owned(), create_value(), &[compound.clone()], &collations);
+        let item = rc_item.read().unwrap();
+
+        assert!(!item.datas.contains_key(&get_unique_int_str("_id")));
+        assert_eq!(item.datas.len(), 4);
+
+        let get_value = |field_int: &str| item.datas[field_int].read().unwrap().get_value().clone();
+        assert_eq!(get_value(&title), to_value("hello world!"));
+        assert_eq!(get_value(&get_len_field_int(&title)), to_value(12));
+        assert_eq!(get_value(&views), to_value(10));
+        assert_eq!(get_value(&compound.field_int), to_value(("hello world!", 10)));
+
+        // The uncollated field is read from the item value, the others own their value.
+        assert_eq!(Arc::strong_count(&item.value), 2);
+        assert!(Arc::ptr_eq(&get_shared_field_int(&views), &get_shared_field_int(&views)));
+    }
+
+    #[test]
+    fn test_release() {
+        let rc_item = RcItem::new("id".to_owned(), create_value(), &[], &HashMap::new());
+        let weak = Arc::downgrade(&rc_item.read().unwrap().value);
+        let datas = rc_item.read().unwrap().datas.clone();
+
+        drop(rc_item);
+        assert!(weak.upgrade().is_some());
+
+        drop(datas);
+        assert!(weak.upgrade().is_none());
     }
 }
@@ -6,7 +6,7 @@ mod _type;
 mod node;
 
 pub use self::data::RcData;
-pub use self::item::RcItem;
+pub use self::item::{RcItem, Item};
 pub use self::child::RcChild;
 pub use self::_type::DataType;
 pub use self::node::RcNode;
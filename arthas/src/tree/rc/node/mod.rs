@@ -1,49 +1,34 @@
-
-use std::sync::RwLock;
+use std::sync::Arc;
 use std::ops::Deref;
-use std::ops::DerefMut;
-use item::Id;
-use super::RcChild;
+use std::fmt;
 use super::super::node::Node;
 
 
-#[derive(Clone, Debug)]
-pub struct RcNode(*mut RwLock<Node>);
+/// Node shared by the versions of a tree, it is never changed once linked.
+#[derive(Clone)]
+pub struct RcNode(Arc<Node>);
 
 impl RcNode {
-    pub fn new(id: Id, rc_child: RcChild) -> RcNode {
-        RcNode(Box::into_raw(Box::new(RwLock::new(Node::new(id, rc_child)))))
+    pub fn new(node: Node) -> RcNode {
+        RcNode(Arc::new(node))
     }
+}
 
-    pub fn destroy(self) {
-        let rc_node = unsafe { Box::from_raw(self.0) };
-        let node = rc_node.write().unwrap();
-        let mut group = node.group.write().unwrap();
-
-        for (_, rc_child) in group.drain() {
-            rc_child.destroy();
-        }
+impl fmt::Debug for RcNode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", *self.0)
     }
 }
 
-unsafe impl Send for RcNode {}
-unsafe impl Sync for RcNode {}
-
 impl Deref for RcNode {
-    type Target = RwLock<Node>;
+    type Target = Node;
     fn deref(&self) -> &Self::Target {
-        unsafe { &*self.0 }
-    }
-}
-
-impl DerefMut for RcNode {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        unsafe { &mut *self.0 }
+        &self.0
     }
 }
 
 impl PartialEq for RcNode {
     fn eq(&self, other: &RcNode) -> bool {
-        self.0 == other.0
+        Arc::ptr_eq(&self.0, &other.0)
     }
 }
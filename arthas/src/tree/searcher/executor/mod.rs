@@ -1,160 +1,386 @@
 
-use std::cmp::Ordering;
-use std::sync::Mutex;
-use std::sync::atomic::AtomicBool;
+use std::cmp::{self, Ordering};
+use std::sync::Arc;
 use std::collections::HashMap;
 use super::task::Task;
 use error::Error;
 use super::Tree;
-use super::super::rc::RcChild;
+use super::super::rc::{RcChild, RcItem, Item};
 use item::FieldInt;
 use serde_json::Value;
-use query::QueryType;
+use query::{QueryType, Order};
 use super::super::node::{Group, Groups};
 use quickersort::sort_by;
 use super::super::math::Math;
 use super::task::{Sub, Orders};
-use scoped_pool::Pool;
-use super::entrance_type::EntranceType;
+use super::super::compound::Plan;
+use super::super::cmp::Cmp;
+use super::super::range::Range;
 
 
+/// Count and items found in a tree.
+type Found = (usize, Vec<RcItem>);
+
 pub struct Exectuor {}
 
 impl Exectuor {
-    pub fn exec(pool: &Pool,
-                tree: &Tree,
-                mut task: Task)
-                -> Result<(usize, Vec<*const Value>), Error> {
-        if tree.id_map.is_empty() {
-            thread_trace!("tree is empty");
-            return Ok(Default::default());
+    /// Items of every tree are found with offset and limit combined, then merged by the orders
+    /// of the task before the offset and limit are applied.
+    pub fn exec(trees: &[&Tree], mut task: Task) -> Result<(usize, Vec<Arc<Value>>), Error> {
+        if let Some(terms) = task.terms.take() {
+            thread_trace!("search text: {:?}", terms);
+            return Ok(to_values(search_text(trees, &task, &terms)));
         }
 
-        let field_groups = Mutex::new(HashMap::new());
-        let field_sub = Mutex::new(HashMap::new());
-        let stopped = AtomicBool::new(false);
-
-        pool.scoped(|scope| {
-            let field_groups = &field_groups;
-            let field_sub = &field_sub;
-            let stopped = &stopped;
-
-            for mut sub in task.subs.drain(..) {
-                let entrance_type = EntranceType::new(tree,
-                                                      &sub.field_int,
-                                                      &sub.comparisions.first().unwrap().other);
-
-                match entrance_type {
-                    EntranceType::Root => {
-                        scope.execute(move || {
-                            let mut groups = Groups::new();
-                            let rc_node = tree.root
-                                .get(&sub.field_int)
-                                .unwrap();
-                            thread_trace!("search root, root is {:?}",
-                                          rc_node.read().unwrap().get_value());
-
-                            rc_node.read()
-                                .unwrap()
-                                .search_root(stopped, &mut groups, &mut sub);
-
-                            field_groups.lock().unwrap().insert(sub.field_int.clone(), groups);
-                            field_sub.lock().unwrap().insert(sub.field_int.clone(), sub);
-                        });
-                    }
-                    EntranceType::Min => {
-                        scope.execute(move || {
-                            let mut groups = Groups::new();
-                            let rc_node = tree.min
-                                .get(&sub.field_int)
-                                .unwrap();
-
-                            thread_trace!("search min, min is {:?}",
-                                          rc_node.read().unwrap().get_value());
-
-                            rc_node.read()
-                                .unwrap()
-                                .search_min(stopped, &mut groups, &mut sub);
-
-                            field_groups.lock().unwrap().insert(sub.field_int.clone(), groups);
-                            field_sub.lock().unwrap().insert(sub.field_int.clone(), sub);
-                        })
-                    }
-                    EntranceType::Max => {
-                        scope.execute(move || {
-                            let mut groups = Groups::new();
-                            let rc_node = tree.max
-                                .get(&sub.field_int)
-                                .unwrap();
-
-                            thread_trace!("search max, max is {:?}",
-                                          rc_node.read().unwrap().get_value());
-
-                            rc_node.read()
-                                .unwrap()
-                                .search_max(stopped, &mut groups, &mut sub);
-
-                            field_groups.lock().unwrap().insert(sub.field_int.clone(), groups);
-                            field_sub.lock().unwrap().insert(sub.field_int.clone(), sub);
-                        })
-                    }
-                    EntranceType::None => {
-                        thread_trace!("no root found!");
-                    }
-                }
-            }
+        if trees.len() == 1 {
+            return Ok(to_values(exec_tree(trees[0], task)));
+        }
+
+        let offset = task.offset;
+        let limit = task.limit;
+        task.offset = 0;
+        task.limit = limit.map(|limit| offset + limit);
+
+        let mut count = 0;
+        let mut rc_items = Vec::new();
+
+        for tree in trees {
+            let found = exec_tree(tree, task.clone());
+            count += found.0;
+            rc_items.extend(found.1);
+        }
+
+        if task.query_type == QueryType::Count {
+            count = count.saturating_sub(offset);
+            return Ok((limit.map_or(count, |limit| cmp::min(count, limit)), Vec::new()));
+        }
+
+        if task.has_order() {
+            sort_by(&mut rc_items[..], &|a: &RcItem, b: &RcItem| {
+                cmp_items(&a.read().unwrap(), &b.read().unwrap(), &task.orders)
+            });
+        }
+
+        task.offset = offset;
+        task.limit = limit;
+        Ok(to_values(take_items(&task, rc_items.iter().collect())))
+    }
+}
+
+#[inline]
+fn to_values(found: Found) -> (usize, Vec<Arc<Value>>) {
+    let (count, rc_items) = found;
+    (count, rc_items.iter().map(|rc_item| rc_item.read().unwrap().get_value()).collect())
+}
+
+fn exec_tree(tree: &Tree, mut task: Task) -> Found {
+    if tree.id_map.is_empty() {
+        thread_trace!("tree is empty");
+        return Default::default();
+    }
+
+    if let Some(plan) = Plan::new(&tree.compounds, &task) {
+        thread_trace!("search compound index: {:?}", plan);
+        return search_compound(tree, task, plan);
+    }
+
+    let hash_position = task.subs.iter().position(|sub| {
+        tree.hashes.contains_key(&sub.field_int) &&
+        sub.comparisions.iter().all(|comparision| comparision.cmp == Cmp::Eq)
+    });
+
+    if let Some(position) = hash_position {
+        let sub = task.subs.remove(position);
+        thread_trace!("search hash index: {:?}", sub);
+        return search_hash(tree, task, sub);
+    }
+
+    let (subs, scan_subs): (Vec<_>, Vec<_>) = task.subs.drain(..).partition(|sub| {
+        tree.indexes.contains(&sub.field_int) &&
+        sub.comparisions.iter().all(|comparision| comparision.cmp != Cmp::Ieq)
+    });
+
+    if subs.is_empty() && scan_subs.is_empty() {
+        if let Some(result) = search_order(tree, &task) {
+            thread_trace!("walk index of order field: {:?}", task.order_field);
+            return result;
+        }
+    }
+
+    if subs.is_empty() {
+        thread_trace!("no indexed condition, scan items");
+        return scan_items(tree, &task, &scan_subs);
+    }
+
+    search_index(tree, task, subs, scan_subs)
+}
+
+/// Walk the most selective indexed sub, the others filter its children.
+#[inline]
+fn search_index(tree: &Tree,
+                task: Task,
+                mut subs: Vec<Sub>,
+                scan_subs: Vec<Sub>)
+                -> Found {
+    let ranges = subs.iter()
+        .map(|sub| Range::new(&sub.comparisions))
+        .collect::<Vec<_>>();
+    let position = get_driving_position(tree, &subs, &ranges);
+
+    let sub = subs.remove(position);
+    let range = &ranges[position];
+    thread_trace!("driving sub: {:?}", sub);
+
+    let sorted = task.orders.len() == 1 && sub.order.is_some();
+    let desc = sorted && sub.order == Some(Order::Desc);
+    let field_sub = subs.into_iter()
+        .chain(scan_subs)
+        .map(|sub| (sub.field_int.clone(), sub))
+        .collect::<HashMap<_, _>>();
+    let limit = if field_sub.is_empty() && task.ttl.is_none() &&
+                   (sorted || !task.has_order()) &&
+                   task.query_type == QueryType::Find {
+        task.limit.map(|limit| task.offset + limit)
+    } else {
+        None
+    };
+
+    let mut groups = Groups::new();
+
+    match tree.root.get(&sub.field_int) {
+        Some(rc_node) => rc_node.search(range, desc, limit, &mut groups),
+        None => thread_trace!("no root found!"),
+    }
+
+    thread_trace!("wait for filter, groups len: {:?}", groups.len());
+
+    if task.has_order() && !sorted {
+        let mut children = groups_to_children(groups);
+        sort_children(&mut children[..], &task.orders);
+        filter_children(children, &task, &field_sub)
+    } else {
+        filter_groups(groups, &task, &field_sub)
+    }
+}
+
+/// Walk the index of the only order field, if every item is in it.
+#[inline]
+fn search_order(tree: &Tree, task: &Task) -> Option<Found> {
+    if task.orders.len() != 1 {
+        return None;
+    }
+
+    let (ref field_int, ref order) = task.orders[0];
+    let complete = tree.root
+        .get(field_int)
+        .map_or(false, |rc_node| rc_node.size == tree.id_map.len());
+
+    if !complete {
+        return None;
+    }
+
+    let desc = *order == Order::Desc;
+    let limit = if task.query_type == QueryType::Find && task.ttl.is_none() {
+        task.limit.map(|limit| task.offset + limit)
+    } else {
+        None
+    };
+    let mut groups = Groups::new();
+    tree.root[field_int].search(&Range::default(), desc, limit, &mut groups);
+    Some(filter_groups(groups, task, &HashMap::new()))
+}
+
+#[inline]
+fn get_driving_position(tree: &Tree, subs: &[Sub], ranges: &[Range]) -> usize {
+    (0..subs.len())
+        .min_by_key(|&i| estimate(tree, &subs[i].field_int, &ranges[i]))
+        .unwrap()
+}
+
+/// Cost of walking the range of the field: its nodes are visited and their children
+/// filtered. Children are counted by the index, nodes estimated from its stats.
+#[inline]
+fn estimate(tree: &Tree, field_int: &str, range: &Range) -> usize {
+    let node = match tree.root.get(field_int) {
+        Some(rc_node) => rc_node,
+        None => return 0,
+    };
+
+    let mut count = node.count(range);
+
+    for value in &range.excluded {
+        if range.is_above_lower(value) && range.is_below_upper(value) {
+            let excluded = node.find_group(value).map_or(0, |group| group.len());
+            count = count.saturating_sub(excluded);
+        }
+    }
+
+    let nodes = tree.stats
+        .get(field_int)
+        .map_or(count, |stats| stats.get_nodes(count, range.is_single()));
+
+    thread_trace!("estimate field: {}, count: {}, nodes: {}", field_int, count, nodes);
+    count + nodes
+}
+
+#[inline]
+fn search_compound(tree: &Tree, mut task: Task, plan: Plan) -> Found {
+    let mut groups = Groups::new();
+
+    if let Some(rc_node) = tree.root.get(&plan.field_int) {
+        rc_node.search(&plan.range, plan.desc, None, &mut groups);
+    }
+
+    let field_sub = task.subs
+        .drain(..)
+        .filter(|sub| !plan.covers(&sub.field_int))
+        .map(|sub| (sub.field_int.clone(), sub))
+        .collect::<HashMap<_, _>>();
+
+    if task.has_order() && !plan.sorted {
+        let mut children = groups_to_children(groups);
+        sort_children(&mut children[..], &task.orders);
+        filter_children(children, &task, &field_sub)
+    } else {
+        filter_groups(groups, &task, &field_sub)
+    }
+}
+
+#[inline]
+fn search_hash(tree: &Tree, mut task: Task, sub: Sub) -> Found {
+    let mut groups = Groups::new();
+    let hash_index = &tree.hashes[&sub.field_int];
+
+    if let Some(group) = hash_index.get(&sub.comparisions[0].other) {
+        groups.push(group);
+    }
+
+    let mut field_sub = task.subs
+        .drain(..)
+        .map(|sub| (sub.field_int.clone(), sub))
+        .collect::<HashMap<_, _>>();
+
+    if sub.comparisions.len() > 1 {
+        field_sub.insert(sub.field_int.clone(), sub);
+    }
+
+    if task.has_order() {
+        let mut children = groups_to_children(groups);
+        sort_children(&mut children[..], &task.orders);
+        filter_children(children, &task, &field_sub)
+    } else {
+        filter_groups(groups, &task, &field_sub)
+    }
+}
+
+#[inline]
+fn scan_items(tree: &Tree, task: &Task, subs: &[Sub]) -> Found {
+    let mut rc_items = tree.id_map
+        .values()
+        .filter(|rc_item| {
+            let item = rc_item.read().unwrap();
+            task.is_alive(&item) && subs.iter().all(|sub| sub.match_item(&item))
+        })
+        .collect::<Vec<_>>();
+
+    if task.has_order() {
+        sort_by(&mut rc_items[..], &|a: &&RcItem, b: &&RcItem| {
+            cmp_items(&a.read().unwrap(), &b.read().unwrap(), &task.orders)
+        });
+    }
+
+    take_items(task, rc_items)
+}
+
+/// Items containing any of the terms, ranked by relevance unless the task has orders. Terms are
+/// weighted by the items of all trees, so the ranking does not depend on how items are split.
+#[inline]
+fn search_text(trees: &[&Tree], task: &Task, terms: &[String]) -> Found {
+    let total = trees.iter().map(|tree| tree.id_map.len()).sum();
+    let frequencies = trees[0].texts
+        .keys()
+        .map(|field_int| {
+            let frequencies = terms.iter()
+                .map(|term| {
+                    trees.iter().map(|tree| tree.texts[field_int].get_frequency(term)).sum()
+                })
+                .collect::<Vec<_>>();
+            (field_int, frequencies)
+        })
+        .collect::<HashMap<_, _>>();
+    let mut ranked = Vec::new();
+
+    for tree in trees {
+        let mut scores = HashMap::new();
+
+        for (field_int, text_index) in &tree.texts {
+            text_index.score(terms, total, &frequencies[field_int], &mut scores);
+        }
+
+        ranked.extend(scores.into_iter()
+            .filter_map(|(id, score)| tree.id_map.get(&id).map(|rc_item| (rc_item, score)))
+            .filter(|&(rc_item, _)| {
+                let item = rc_item.read().unwrap();
+                task.is_alive(&item) && task.subs.iter().all(|sub| sub.match_item(&item))
+            }));
+    }
+
+    if task.has_order() {
+        sort_by(&mut ranked[..], &|a: &(&RcItem, f64), b: &(&RcItem, f64)| {
+            cmp_items(&a.0.read().unwrap(), &b.0.read().unwrap(), &task.orders)
         });
+    } else {
+        sort_by(&mut ranked[..], &|a: &(&RcItem, f64), b: &(&RcItem, f64)| {
+            b.1.partial_cmp(&a.1)
+                .unwrap_or(Ordering::Equal)
+                .then_with(|| a.0.read().unwrap().id.cmp(&b.0.read().unwrap().id))
+        });
+    }
 
-        let mut field_groups = field_groups.into_inner().unwrap();
-        let mut field_sub = field_sub.into_inner().unwrap();
-        let need_sort = task.has_order();
-        let stopped_field = get_stopped_field(&field_sub, &task);
+    take_items(task, ranked.into_iter().map(|(rc_item, _)| rc_item).collect())
+}
 
-        thread_trace!("stopped field: {:?}", stopped_field);
+#[inline]
+fn take_items(task: &Task, rc_items: Vec<&RcItem>) -> Found {
+    let mut count = 0;
+    let mut found = Vec::new();
 
-        if field_groups.contains_key(&stopped_field) {
-            field_sub.remove(&stopped_field);
-            let groups = field_groups.remove(&stopped_field).unwrap();
+    for rc_item in rc_items.into_iter().skip(task.offset) {
+        count += 1;
 
-            thread_trace!("wait for filter, groups len: {:?}", groups.len());
+        if task.query_type == QueryType::Find {
+            found.push(rc_item.clone());
+        }
 
-            if need_sort {
-                let mut children = groups_to_children(groups);
-                thread_trace!("children: {:?}", children);
-                sort_children(&mut children[..], &task.orders);
-                thread_trace!("sorted children: {:?}", children);
-                Ok(filter_children(children, &task, &field_sub))
-            } else {
-                Ok(filter_groups(groups, &task, &field_sub))
-            }
-        } else {
-            unreachable!()
+        if task.limit.is_some() && count >= *task.limit.as_ref().unwrap() {
+            break;
         }
     }
+
+    (count, found)
 }
 
 #[inline]
 fn filter_groups(groups: Groups,
                  task: &Task,
                  field_sub: &HashMap<FieldInt, Sub>)
-                 -> (usize, Vec<*const Value>) {
+                 -> Found {
     let mut found = 0;
     let mut count = 0;
-    let mut values = Vec::new();
-    let other_conditions_exists = !field_sub.is_empty();
+    let mut rc_items = Vec::new();
+    let other_conditions_exists = !field_sub.is_empty() || task.ttl.is_some();
     let is_count = task.query_type == QueryType::Count;
 
     'outer: for group in groups {
-        thread_trace!("filter groups, current group: {:?}",
-                      &*group.read().unwrap());
+        thread_trace!("filter groups, current group: {:?}", group);
 
         if other_conditions_exists {
             thread_trace!("other conditions exists");
-            for rc_child in group.read().unwrap().values() {
+            for rc_child in group.values() {
                 if filter_child(rc_child,
                                 &mut found,
                                 &mut count,
-                                &mut values,
+                                &mut rc_items,
                                 task,
                                 field_sub) {
                     break 'outer;
@@ -162,11 +388,11 @@ fn filter_groups(groups: Groups,
             }
         } else if is_count {
             thread_trace!("only count");
-            count += group.read().unwrap().len();
+            count += group.len();
             found = count;
         } else {
             thread_trace!("no other conditions exists");
-            if collect_child(&mut found, &mut count, task, &group, &mut values) {
+            if collect_child(&mut found, &mut count, task, &group, &mut rc_items) {
                 break;
             }
         }
@@ -180,7 +406,7 @@ fn filter_groups(groups: Groups,
         }
     }
 
-    (count, values)
+    (count, rc_items)
 }
 
 #[inline]
@@ -188,14 +414,14 @@ fn collect_child(found: &mut usize,
                  count: &mut usize,
                  task: &Task,
                  group: &Group,
-                 values: &mut Vec<*const Value>)
+                 rc_items: &mut Vec<RcItem>)
                  -> bool {
-    for rc_child in group.read().unwrap().values() {
+    for rc_child in group.values() {
         *found += 1;
 
         if *found > task.offset {
             *count += 1;
-            values.push(rc_child.read().unwrap().get_item_pointer());
+            rc_items.push(rc_child.read().unwrap().item.clone());
 
             if task.limit.is_some() && *count >= *task.limit.as_ref().unwrap() {
                 return true;
@@ -210,33 +436,37 @@ fn collect_child(found: &mut usize,
 fn filter_children(children: Vec<RcChild>,
                    task: &Task,
                    field_sub: &HashMap<FieldInt, Sub>)
-                   -> (usize, Vec<*const Value>) {
+                   -> Found {
     let mut found = 0;
     let mut count = 0;
-    let mut values = Vec::new();
+    let mut rc_items = Vec::new();
 
     for rc_child in children {
         if filter_child(&rc_child,
                         &mut found,
                         &mut count,
-                        &mut values,
+                        &mut rc_items,
                         task,
                         field_sub) {
             break;
         }
     }
 
-    (count, values)
+    (count, rc_items)
 }
 
 #[inline]
 fn filter_child(rc_child: &RcChild,
                 found: &mut usize,
                 count: &mut usize,
-                values: &mut Vec<*const Value>,
+                rc_items: &mut Vec<RcItem>,
                 task: &Task,
                 field_sub: &HashMap<FieldInt, Sub>)
                 -> bool {
+    if !task.is_alive(&rc_child.read().unwrap().item.read().unwrap()) {
+        return false;
+    }
+
     let mut pass = true;
 
     for sub in field_sub.values() {
@@ -258,7 +488,7 @@ fn filter_child(rc_child: &RcChild,
 
         if *found > task.offset {
             *count += 1;
-            values.push(rc_child.read().unwrap().get_item_pointer());
+            rc_items.push(rc_child.read().unwrap().item.clone());
         }
 
         if task.limit.is_some() && *count >= *task.limit.as_ref().unwrap() {
@@ -279,52 +509,171 @@ fn sort_children(children: &mut [RcChild], orders: &Orders) {
         let b_rc_item = b.read().unwrap();
         let b_item = b_rc_item.item.read().unwrap();
 
-        let mut ordering = Ordering::Equal;
-
-        for &(ref field, ref order) in orders {
-            let a_rc_data = &a_item.datas[field];
-            let a_data = a_rc_data.read().unwrap();
-            let a_value = a_data.get_value();
-
+        cmp_items(&a_item, &b_item, orders)
+    });
+}
 
-            let b_rc_data = &b_item.datas[field];
-            let b_data = b_rc_data.read().unwrap();
-            let b_value = b_data.get_value();
+/// Items missing the field come first in ascending order.
+#[inline]
+fn cmp_items(a_item: &Item, b_item: &Item, orders: &Orders) -> Ordering {
+    for &(ref field, ref order) in orders {
+        let a_data = a_item.datas.get(field).map(|rc_data| rc_data.read().unwrap());
+        let b_data = b_item.datas.get(field).map(|rc_data| rc_data.read().unwrap());
+
+        let ordering = match (a_data, b_data) {
+            (Some(a_data), Some(b_data)) => cmp_values(a_data.get_value(), b_data.get_value()),
+            (Some(_), None) => Ordering::Greater,
+            (None, Some(_)) => Ordering::Less,
+            (None, None) => Ordering::Equal,
+        };
+
+        if ordering != Ordering::Equal {
+            return match *order {
+                Order::Asc => ordering,
+                Order::Desc => ordering.reverse(),
+            };
+        }
+    }
 
-            ordering = a_value.cmp(b_value, order);
+    Ordering::Equal
+}
 
-            if ordering != Ordering::Equal {
-                break;
+/// Total order of values, different types are ordered by `get_type_rank`.
+fn cmp_values(a: &Value, b: &Value) -> Ordering {
+    match (a, b) {
+        (&Value::Bool(a), &Value::Bool(b)) => a.cmp(&b),
+        (&Value::Number(_), &Value::Number(_)) => Math::cmp(a, b, &Order::Asc),
+        (&Value::String(ref a), &Value::String(ref b)) => a.cmp(b),
+        (&Value::Array(ref a), &Value::Array(ref b)) => {
+            for (a_value, b_value) in a.iter().zip(b) {
+                let ordering = cmp_values(a_value, b_value);
+
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
             }
+
+            a.len().cmp(&b.len())
         }
+        _ => get_type_rank(a).cmp(&get_type_rank(b)),
+    }
+}
 
-        ordering
-    });
+#[inline]
+fn get_type_rank(value: &Value) -> usize {
+    match *value {
+        Value::Null => 0,
+        Value::Bool(_) => 1,
+        Value::Number(_) => 2,
+        Value::String(_) => 3,
+        Value::Array(_) => 4,
+        Value::Object(_) => 5,
+    }
 }
 
 #[inline]
 fn groups_to_children(groups: Vec<Group>) -> Vec<RcChild> {
     let mut children = Vec::new();
     for group in groups {
-        for rc_child in group.read().unwrap().values() {
+        for rc_child in group.values() {
             children.push(rc_child.clone());
         }
     }
     children
 }
 
-#[inline]
-fn get_stopped_field(field_sub: &HashMap<FieldInt, Sub>, task: &Task) -> String {
-    if task.has_order() && field_sub.contains_key(task.get_order_field()) &&
-       field_sub.get(task.get_order_field()).unwrap().stopped {
-        return task.get_order_field().to_owned();
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use serde_json::{Map, Value};
+    use to_value;
+    use super::{get_driving_position, Sub, Range};
+    use super::super::super::Tree;
+    use super::super::super::stats::Stats;
+    use super::super::super::cmp::Cmp;
+    use super::super::super::comparision::Comparision;
+
+    const UNIQUE: &'static str = "1";
+    const FEW: &'static str = "2";
+
+    /// Values of `UNIQUE` are 0 to 999, values of `FEW` are 0 to 3.
+    fn create_tree() -> Tree {
+        let mut tree = Tree {
+            int_field_map: HashMap::new(),
+            indexes: vec![UNIQUE.to_owned(), FEW.to_owned()].into_iter().collect(),
+            compounds: Vec::new(),
+            hashes: HashMap::new(),
+            collations: HashMap::new(),
+            texts: HashMap::new(),
+            ttl: None,
+            id_map: Default::default(),
+            root: HashMap::new(),
+            stats: HashMap::new(),
+        };
+
+        for i in 0..1000 {
+            let mut object = Map::new();
+            object.insert(UNIQUE.to_owned(), to_value(i));
+            object.insert(FEW.to_owned(), to_value(i % 4));
+
+            let mut value = Map::new();
+            value.insert("item".to_owned(), Value::Object(object));
+            tree.insert(i.to_string(), Value::Object(value));
+        }
+
+        tree
+    }
+
+    fn create_sub(field_int: &str, cmp: Cmp, other: usize) -> Sub {
+        Sub {
+            field_int: field_int.to_owned(),
+            comparisions: vec![Comparision::new(field_int.to_owned(), cmp, to_value(other))],
+            order: None,
+        }
+    }
+
+    fn get_driving_field(tree: &Tree, subs: &[Sub]) -> String {
+        let ranges = subs.iter()
+            .map(|sub| Range::new(&sub.comparisions))
+            .collect::<Vec<_>>();
+        subs[get_driving_position(tree, subs, &ranges)].field_int.clone()
     }
 
-    for (field, sub) in field_sub {
-        if sub.stopped {
-            return field.to_owned();
+    #[test]
+    fn test_stats() {
+        let mut tree = create_tree();
+        assert_eq!(tree.stats[UNIQUE], Stats { children: 1000, distinct: 1000 });
+        assert_eq!(tree.stats[FEW], Stats { children: 1000, distinct: 4 });
+
+        for i in 0..1000 {
+            if i % 4 == 1 || i >= 500 {
+                tree.delete(&i.to_string());
+            }
         }
+
+        assert_eq!(tree.stats[UNIQUE], Stats { children: 375, distinct: 375 });
+        assert_eq!(tree.stats[FEW], Stats { children: 375, distinct: 3 });
     }
 
-    unreachable!()
+    #[test]
+    fn test_driving_field() {
+        let tree = create_tree();
+
+        // 100 nodes of one child against one node of 250 children.
+        let subs = vec![create_sub(FEW, Cmp::Eq, 1), create_sub(UNIQUE, Cmp::Lt, 100)];
+        assert_eq!(get_driving_field(&tree, &subs), UNIQUE);
+
+        // 200 nodes of one child against one node of 250 children: fewer nodes to visit
+        // outweigh the extra children.
+        let subs = vec![create_sub(UNIQUE, Cmp::Lt, 200), create_sub(FEW, Cmp::Eq, 1)];
+        assert_eq!(get_driving_field(&tree, &subs), FEW);
+
+        // No child in the range.
+        let subs = vec![create_sub(UNIQUE, Cmp::Lt, 200), create_sub(FEW, Cmp::Eq, 4)];
+        assert_eq!(get_driving_field(&tree, &subs), FEW);
+
+        let subs = vec![create_sub(FEW, Cmp::Ge, 2), create_sub(UNIQUE, Cmp::Ge, 990)];
+        assert_eq!(get_driving_field(&tree, &subs), UNIQUE);
+    }
 }
@@ -17,6 +17,7 @@ impl Meet for Value {
             Cmp::Ge => Math::ge(self, other),
             Cmp::Le => Math::le(self, other),
             Cmp::Ne => Math::ne(self, other),
+            Cmp::Ieq => Math::ieq(self, other),
         }
     }
 }
@@ -2,8 +2,8 @@
 pub mod task;
 pub mod executor;
 pub mod meet;
-pub mod entrance_type;
 
+use std::sync::Arc;
 use super::Tree;
 use query::{Query, QueryType};
 use error::Error;
@@ -11,7 +11,8 @@ use serde_json::Value;
 use traits::Structure;
 use self::task::Task;
 use self::executor::Exectuor;
-use scoped_pool::Pool;
+use super::ttl;
+use super::rc::RcItem;
 
 
 pub struct Searcher {}
@@ -21,38 +22,44 @@ impl Searcher {
         Searcher {}
     }
 
+    /// Search the trees as one, they hold different items of the same struct.
     pub fn search<T: Structure>(&self,
-                                pool: &Pool,
-                                tree: &Tree,
+                                trees: &[&Tree],
                                 query: &Query<T>,
                                 query_type: &QueryType)
-                                -> Result<(usize, Vec<*const Value>), Error> {
+                                -> Result<(usize, Vec<Arc<Value>>), Error> {
         if query.id.is_some() {
             thread_trace!("search by id: {:?}", query.id);
-            self.search_by_id(tree, query, query_type)
-        } else if !query.conditions.is_empty() {
-            thread_trace!("search by conditions.");
-            self.search_by_query(pool, tree, query, query_type)
+            self.search_by_id(trees, query, query_type)
+        } else if !query.conditions.is_empty() || !query.orders.is_empty() ||
+                  query.text.is_some() {
+            thread_trace!("search by text, conditions and orders.");
+            self.search_by_query(trees, query, query_type)
         } else {
             thread_trace!("search all.");
-            self.search_all(tree, query, query_type)
+            self.search_all(trees, query, query_type)
         }
     }
 
     fn search_by_id<T: Structure>(&self,
-                                  tree: &Tree,
+                                  trees: &[&Tree],
                                   query: &Query<T>,
                                   query_type: &QueryType)
-                                  -> Result<(usize, Vec<*const Value>), Error> {
+                                  -> Result<(usize, Vec<Arc<Value>>), Error> {
         let mut values = Vec::new();
         let mut count = 0;
-
-        if let Some(rc_item) = tree.id_map.get(query.id.as_ref().unwrap()) {
-            if query.offset.is_none() || *query.offset.as_ref().unwrap() == 0 {
+        let id = query.id.as_ref().unwrap();
+        let found = trees.iter()
+            .filter_map(|tree| tree.id_map.get(id).map(|rc_item| (tree, rc_item)))
+            .next();
+
+        if let Some((tree, rc_item)) = found {
+            if is_alive(tree, rc_item, ttl::now()) &&
+               (query.offset.is_none() || *query.offset.as_ref().unwrap() == 0) {
                 count += 1;
 
                 if query_type == &QueryType::Find {
-                    values.push(rc_item.read().unwrap().get_pointer());
+                    values.push(rc_item.read().unwrap().get_value());
                 }
             }
         }
@@ -61,25 +68,30 @@ impl Searcher {
     }
 
     fn search_by_query<T: Structure>(&self,
-                                     pool: &Pool,
-                                     tree: &Tree,
+                                     trees: &[&Tree],
                                      query: &Query<T>,
                                      query_type: &QueryType)
-                                     -> Result<(usize, Vec<*const Value>), Error> {
-        Exectuor::exec(pool, tree, Task::new(query, query_type))
+                                     -> Result<(usize, Vec<Arc<Value>>), Error> {
+        let mut task = Task::new(query, query_type);
+        task.collate(&trees[0].collations);
+        task.ttl = trees[0].ttl.clone();
+        Exectuor::exec(trees, task)
     }
 
     fn search_all<T: Structure>(&self,
-                                tree: &Tree,
+                                trees: &[&Tree],
                                 query: &Query<T>,
                                 query_type: &QueryType)
-                                -> Result<(usize, Vec<*const Value>), Error> {
+                                -> Result<(usize, Vec<Arc<Value>>), Error> {
         let mut values = Vec::new();
         let mut count = 0;
 
-        thread_trace!("current id map length: {}", tree.id_map.len());
+        let now = ttl::now();
+        let rc_items = trees.iter().flat_map(|tree| {
+            tree.id_map.values().filter(move |rc_item| is_alive(tree, rc_item, now))
+        });
 
-        for (index, rc_item) in tree.id_map.values().enumerate() {
+        for (index, rc_item) in rc_items.enumerate() {
             if query.offset.is_some() && index + 1 < *query.offset.as_ref().unwrap() {
                 continue;
             }
@@ -87,7 +99,7 @@ impl Searcher {
             count += 1;
 
             if query_type == &QueryType::Find {
-                values.push(rc_item.read().unwrap().get_pointer());
+                values.push(rc_item.read().unwrap().get_value());
             }
 
             if query.limit.is_some() && count >= *query.limit.as_ref().unwrap() {
@@ -98,3 +110,8 @@ impl Searcher {
         Ok((count, values))
     }
 }
+
+#[inline]
+fn is_alive(tree: &Tree, rc_item: &RcItem, now: f64) -> bool {
+    tree.ttl.as_ref().map_or(true, |ttl| !ttl.is_expired(&rc_item.read().unwrap(), now))
+}
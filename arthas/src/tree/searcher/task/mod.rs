@@ -3,24 +3,34 @@ use std::collections::HashSet;
 use super::super::comparision::Comparision;
 use item::FieldInt;
 use query::{Query, Order, QueryType};
-use super::super::rc::RcChild;
+use super::super::rc::{RcChild, Item};
+use super::super::cmp::Cmp;
+use super::super::collation::{Collation, Collations};
+use super::super::text::tokenize;
+use super::super::ttl::{self, Ttl};
 
 pub type Subs = Vec<Sub>;
 pub type Orders = Vec<(String, Order)>;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Sub {
     pub field_int: FieldInt,
     pub comparisions: Vec<Comparision>,
     pub order: Option<Order>,
-    pub stopped: bool,
 }
 
 impl Sub {
     pub fn _match(&self, rc_child: &RcChild) -> bool {
         let child = rc_child.read().unwrap();
         let item = child.item.read().unwrap();
-        let rc_data = item.datas.get(&self.field_int).unwrap();
+        self.match_item(&item)
+    }
+
+    pub fn match_item(&self, item: &Item) -> bool {
+        let rc_data = match item.datas.get(&self.field_int) {
+            Some(rc_data) => rc_data,
+            None => return false,
+        };
         let data = rc_data.read().unwrap();
         let value = data.get_value();
 
@@ -34,7 +44,7 @@ impl Sub {
 }
 
 
-#[derive(Default, Debug)]
+#[derive(Default, Debug, Clone)]
 pub struct Task {
     pub subs: Subs,
     pub orders: Orders,
@@ -45,6 +55,9 @@ pub struct Task {
     pub query_type: QueryType,
     pub subs_length: usize,
     pub current: usize,
+    pub terms: Option<Vec<String>>,
+    pub ttl: Option<Ttl>,
+    pub now: f64,
 }
 
 impl Task {
@@ -72,6 +85,9 @@ impl Task {
             subs_length: subs_length,
             order_field: order_field,
             current: 0,
+            terms: query.text.as_ref().map(|text| tokenize(text)),
+            ttl: None,
+            now: ttl::now(),
         }
     }
 
@@ -110,15 +126,29 @@ impl Task {
                 field_int: field_int.to_owned(),
                 comparisions: comparisions.clone(),
                 order: sub_order,
-                stopped: false,
             });
         }
 
         (subs_length, subs)
     }
 
-    pub fn get_order_field(&self) -> &str {
-        self.order_field.as_ref().unwrap()
+    /// Compare collated fields by their keys, `ieq` on a nocase field is an `eq`.
+    pub fn collate(&mut self, collations: &Collations) {
+        for sub in &mut self.subs {
+            if let Some(collation) = collations.get(&sub.field_int) {
+                for comparision in &mut sub.comparisions {
+                    comparision.other = collation.collate(&comparision.other);
+
+                    if *collation == Collation::NoCase && comparision.cmp == Cmp::Ieq {
+                        comparision.cmp = Cmp::Eq;
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn is_alive(&self, item: &Item) -> bool {
+        self.ttl.as_ref().map_or(true, |ttl| !ttl.is_expired(item, self.now))
     }
 
     pub fn has_order(&self) -> bool {
@@ -0,0 +1,25 @@
+use std::cmp;
+
+
+/// Counts of an index, kept up to date by `Inserter` and `Deleter`.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Stats {
+    /// Children in the index.
+    pub children: usize,
+    /// Distinct values, one node each.
+    pub distinct: usize,
+}
+
+impl Stats {
+    /// Nodes holding `count` children, assuming groups of the average size. A single value
+    /// is always one node.
+    pub fn get_nodes(&self, count: usize, is_single: bool) -> usize {
+        if count == 0 || self.children == 0 {
+            0
+        } else if is_single {
+            1
+        } else {
+            cmp::max(1, (count * self.distinct + self.children - 1) / self.children)
+        }
+    }
+}
@@ -0,0 +1,131 @@
+use std::collections::HashMap;
+use serde_json::Value;
+use im::HashMap as SharedMap;
+use item::Id;
+
+
+static SUFFIXES: &'static [(&'static str, &'static str)] =
+    &[("sses", "ss"), ("ies", "y"), ("ing", ""), ("ed", ""), ("s", "")];
+
+
+/// Inverted index of a text field, term to the ids and how often the term occurs. Copies share
+/// the postings they do not change.
+#[derive(Default, Clone)]
+pub struct TextIndex {
+    postings: SharedMap<String, SharedMap<Id, usize>>,
+    terms: SharedMap<Id, Vec<String>>,
+}
+
+impl TextIndex {
+    pub fn new() -> TextIndex {
+        TextIndex { ..Default::default() }
+    }
+
+    /// Strings and arrays of strings are indexed, other values are ignored.
+    pub fn insert(&mut self, value: &Value, id: Id) {
+        let mut terms = Vec::new();
+        collect_terms(value, &mut terms);
+
+        if terms.is_empty() {
+            return;
+        }
+
+        for term in &terms {
+            *self.postings
+                .entry(term.clone())
+                .or_insert_with(SharedMap::new)
+                .entry(id.clone())
+                .or_insert(0) += 1;
+        }
+
+        self.terms.insert(id, terms);
+    }
+
+    pub fn delete(&mut self, id: &str) {
+        if let Some(terms) = self.terms.remove(id) {
+            for term in terms {
+                let is_empty = match self.postings.get_mut(&term) {
+                    Some(ids) => {
+                        ids.remove(id);
+                        ids.is_empty()
+                    }
+                    None => false,
+                };
+
+                if is_empty {
+                    self.postings.remove(&term);
+                }
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.postings.clear();
+        self.terms.clear();
+    }
+
+    /// Number of ids containing the term.
+    pub fn get_frequency(&self, term: &str) -> usize {
+        self.postings.get(term).map_or(0, |ids| ids.len())
+    }
+
+    /// Add the tf-idf of every term to the scores of the ids containing it, `frequencies` are
+    /// the numbers of ids containing the terms out of `total`.
+    pub fn score(&self,
+                 terms: &[String],
+                 total: usize,
+                 frequencies: &[usize],
+                 scores: &mut HashMap<Id, f64>) {
+        for (term, frequency) in terms.iter().zip(frequencies) {
+            if let Some(ids) = self.postings.get(term) {
+                let idf = (1.0 + total as f64 / *frequency as f64).ln();
+
+                for (id, frequency) in ids {
+                    *scores.entry(id.clone()).or_insert(0.0) += *frequency as f64 * idf;
+                }
+            }
+        }
+    }
+}
+
+/// Lowercased and stemmed words of the text.
+pub fn tokenize(text: &str) -> Vec<String> {
+    text.split(|ch: char| !ch.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| stem(word.to_lowercase()))
+        .collect()
+}
+
+fn collect_terms(value: &Value, terms: &mut Vec<String>) {
+    match *value {
+        Value::String(ref text) => terms.extend(tokenize(text)),
+        Value::Array(ref values) => {
+            for value in values {
+                collect_terms(value, terms);
+            }
+        }
+        _ => (),
+    }
+}
+
+/// Strip plural and verb suffixes and a trailing `e`, so `stores`, `stored` and `storing` match.
+fn stem(mut word: String) -> String {
+    for &(suffix, replacement) in SUFFIXES {
+        if word.ends_with(suffix) && word.chars().count() >= suffix.len() + 3 {
+            if suffix == "s" && word.ends_with("ss") {
+                break;
+            }
+
+            let len = word.len() - suffix.len();
+            word.truncate(len);
+            word.push_str(replacement);
+            break;
+        }
+    }
+
+    if word.ends_with('e') && word.chars().count() > 3 {
+        word.pop();
+    }
+
+    word
+}
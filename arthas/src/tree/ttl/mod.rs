@@ -0,0 +1,34 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+use item::FieldInt;
+use traits::get_unique_int_str;
+use super::rc::Item;
+
+
+/// Items expire `seconds` after the unix time of the field, zero or no time never expires.
+#[derive(Debug, Clone)]
+pub struct Ttl {
+    pub field_int: FieldInt,
+    pub seconds: u64,
+}
+
+impl Ttl {
+    pub fn new(field: &str, seconds: u64) -> Ttl {
+        Ttl {
+            field_int: get_unique_int_str(field),
+            seconds: seconds,
+        }
+    }
+
+    pub fn is_expired(&self, item: &Item, now: f64) -> bool {
+        match item.value["item"].get(&self.field_int).and_then(|time| time.as_f64()) {
+            Some(time) if time > 0.0 => time + self.seconds as f64 <= now,
+            _ => false,
+        }
+    }
+}
+
+/// Current unix time in seconds.
+pub fn now() -> f64 {
+    let duration = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+    duration.as_secs() as f64 + duration.subsec_nanos() as f64 / 1_000_000_000.0
+}
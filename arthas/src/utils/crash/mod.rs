@@ -0,0 +1,18 @@
+#[cfg(debug_assertions)]
+use std::env;
+#[cfg(debug_assertions)]
+use std::process;
+
+
+/// Abort at the step named by `ARTHAS_CRASH_POINT`, so tests can recover from every state
+/// a crash leaves the files in. Does nothing in release builds.
+#[cfg(debug_assertions)]
+pub fn point(step: &str) {
+    if env::var("ARTHAS_CRASH_POINT").ok().map_or(false, |point| point == step) {
+        process::abort();
+    }
+}
+
+#[cfg(not(debug_assertions))]
+#[inline]
+pub fn point(_: &str) {}
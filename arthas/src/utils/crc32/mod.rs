@@ -0,0 +1,32 @@
+
+lazy_static! {
+    static ref TABLE: Vec<u32> = (0..256)
+        .map(|index| {
+            (0..8).fold(index as u32, |crc, _| if crc & 1 == 1 {
+                0xedb8_8320 ^ (crc >> 1)
+            } else {
+                crc >> 1
+            })
+        })
+        .collect();
+}
+
+
+/// CRC-32 (IEEE) of the bytes.
+pub fn checksum(bytes: &[u8]) -> u32 {
+    !bytes.iter().fold(!0, |crc, &byte| TABLE[((crc ^ byte as u32) & 0xff) as usize] ^ (crc >> 8))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::checksum;
+
+    #[test]
+    fn test_checksum() {
+        assert_eq!(checksum(b""), 0);
+        assert_eq!(checksum(b"123456789"), 0xcbf4_3926);
+        assert_eq!(checksum(b"The quick brown fox jumps over the lazy dog"),
+                   0x414f_a339);
+    }
+}
@@ -0,0 +1,67 @@
+use std::io::prelude::*;
+use lz4_flex::frame::{FrameEncoder, FrameDecoder, FrameInfo};
+use error::Error;
+
+
+/// A LZ4 frame holding the length of the input. A frame cut short may still decode, records
+/// are checked by their checksums before.
+pub fn compress(input: &[u8]) -> Vec<u8> {
+    let frame_info = FrameInfo::new().content_size(Some(input.len() as u64));
+    let mut encoder = FrameEncoder::with_frame_info(frame_info, Vec::new());
+    encoder.write_all(input).unwrap();
+    encoder.finish().unwrap()
+}
+
+/// Empty input holds no frame, it is refused like other corrupt input.
+pub fn decompress(input: &[u8]) -> Result<Vec<u8>, Error> {
+    if input.is_empty() {
+        return Err(Error::Corrupt("empty compressed record".to_owned()));
+    }
+
+    let mut output = Vec::new();
+    FrameDecoder::new(input)
+        .read_to_end(&mut output)
+        .map_err(|err| Error::Corrupt(format!("invalid compressed record: {}", err)))?;
+    Ok(output)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::{compress, decompress};
+
+    fn round_trip(input: &[u8]) -> Vec<u8> {
+        let compressed = compress(input);
+        assert_eq!(decompress(&compressed).unwrap(), input);
+        compressed
+    }
+
+    fn random_bytes(len: usize) -> Vec<u8> {
+        let mut seed = 7u32;
+        (0..len)
+            .map(|_| {
+                seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                (seed >> 16) as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        round_trip(b"");
+        round_trip(b"a");
+        round_trip(&random_bytes(5000));
+        assert!(round_trip(&vec![b'a'; 100_000]).len() < 1000);
+    }
+
+    #[test]
+    fn test_record() {
+        let record = br#"{"item":{"1":"Hello world! Hello world!","2":"Hello world! Hello world!"}}"#;
+        assert!(round_trip(record).len() < record.len());
+    }
+
+    #[test]
+    fn test_corrupt() {
+        assert!(decompress(b"Hello world!").is_err());
+    }
+}
@@ -1,3 +1,6 @@
 
 pub mod file;
 pub mod hash_map;
+pub mod crc32;
+pub mod crash;
+pub mod lz;
@@ -0,0 +1,101 @@
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::Sender;
+use serde_json::Value;
+use traits::{Structure, FieldIntMap};
+use item::Id;
+use item::convertor::Convertor;
+use tree::rc::RcItem;
+use tree::searcher::task::Sub;
+
+
+/// Change of an item, received from `Query::watch()`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event<T> {
+    /// Item inserted.
+    Inserted(T),
+    /// Item updated or replaced.
+    Updated {
+        /// Item before the change.
+        before: T,
+        /// Item after the change.
+        after: T,
+    },
+    /// Item removed or expired.
+    Removed(T),
+}
+
+impl<T> Event<T> {
+    fn map<U, F: Fn(&T) -> U>(&self, f: F) -> Event<U> {
+        match *self {
+            Event::Inserted(ref item) => Event::Inserted(f(item)),
+            Event::Updated { ref before, ref after } => {
+                Event::Updated {
+                    before: f(before),
+                    after: f(after),
+                }
+            }
+            Event::Removed(ref item) => Event::Removed(f(item)),
+        }
+    }
+}
+
+
+type Notify = Box<Fn(Event<Arc<Value>>) -> bool + Send + Sync>;
+
+/// Conditions of a watch query and the channel its events are sent to.
+pub struct Watcher {
+    id: Option<Id>,
+    subs: Vec<Sub>,
+    send: Notify,
+}
+
+impl Watcher {
+    pub fn new<T: Structure>(id: Option<Id>,
+                             subs: Vec<Sub>,
+                             sender: Sender<Event<T>>,
+                             int_field_map: Arc<FieldIntMap>)
+                             -> Watcher {
+        let sender = Mutex::new(sender);
+
+        Watcher {
+            id: id,
+            subs: subs,
+            send: Box::new(move |event: Event<Arc<Value>>| {
+                let event = event.map(|value| value.to_wrapper::<T>(&int_field_map));
+
+                let event = match event {
+                    Event::Inserted(Ok(wrapper)) => Event::Inserted(wrapper.item),
+                    Event::Updated { before: Ok(before), after: Ok(after) } => {
+                        Event::Updated {
+                            before: before.item,
+                            after: after.item,
+                        }
+                    }
+                    Event::Removed(Ok(wrapper)) => Event::Removed(wrapper.item),
+                    _ => return true,
+                };
+
+                sender.lock().unwrap().send(event).is_ok()
+            }),
+        }
+    }
+
+    /// Send the event if it matches, `false` once the receiver is dropped.
+    pub fn notify(&self, event: &Event<RcItem>) -> bool {
+        let matched = match *event {
+            Event::Inserted(ref rc_item) |
+            Event::Removed(ref rc_item) => self.is_match(rc_item),
+            Event::Updated { ref before, ref after } => {
+                self.is_match(before) || self.is_match(after)
+            }
+        };
+
+        !matched || (self.send)(event.map(|rc_item| rc_item.read().unwrap().get_value()))
+    }
+
+    fn is_match(&self, rc_item: &RcItem) -> bool {
+        let item = rc_item.read().unwrap();
+        self.id.as_ref().map_or(true, |id| *id == item.id) &&
+        self.subs.iter().all(|sub| sub.match_item(&item))
+    }
+}
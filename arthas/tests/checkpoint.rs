@@ -0,0 +1,85 @@
+#[macro_use]
+extern crate serde_derive;
+#[macro_use]
+extern crate arthas_derive;
+#[macro_use]
+extern crate lazy_static;
+extern crate rand;
+extern crate arthas;
+extern crate env_logger;
+extern crate mktemp;
+
+pub mod common;
+pub mod model;
+
+use model::*;
+use mktemp::Temp;
+use std::fs;
+use std::path::PathBuf;
+use std::thread;
+use std::time::Duration;
+
+
+lazy_static! {
+    static ref PATH: PathBuf = {
+        let path = Temp::new_dir().unwrap().to_path_buf();
+        arthas::config::path(&path);
+        arthas::config::checkpoint_interval(Duration::from_millis(50));
+        path
+    };
+}
+
+
+#[test]
+fn test_checkpoint() {
+    let path = &*PATH;
+
+    let log_path = path.join("Article.arl");
+    let data_path = path.join("Article.ar");
+
+    Article::session().count().unwrap();
+    let header = fs::metadata(&log_path).unwrap().len();
+
+    for i in 0..10 {
+        Article::session().insert(Article::new("Foobar!").views(i)).unwrap();
+    }
+
+    thread::sleep(Duration::from_millis(500));
+
+    assert_eq!(fs::metadata(&log_path).unwrap().len(), header);
+    assert!(fs::metadata(&data_path).unwrap().len() > 0);
+
+    Article::session().field("views").lt(5).remove().unwrap();
+    Article::session().insert(Article::new("Hello!").views(10)).unwrap();
+    assert!(fs::metadata(&log_path).unwrap().len() > header);
+
+    thread::sleep(Duration::from_millis(500));
+
+    assert_eq!(fs::metadata(&log_path).unwrap().len(), header);
+
+    arthas::load::<Article>().unwrap();
+    assert_eq!(Article::session().count().unwrap(), 6);
+    assert_eq!(Article::session().field("title").eq("Hello!").count().unwrap(), 1);
+}
+
+#[test]
+fn test_compact_after_checkpoint() {
+    let data_path = PATH.join("Page.ar");
+
+    for i in 0..20 {
+        Page::session().insert(Page::new(format!("Page {:02}", i), "x".repeat(100))).unwrap();
+    }
+
+    thread::sleep(Duration::from_millis(500));
+
+    let size = fs::metadata(&data_path).unwrap().len();
+    Page::session().field("title").lt("Page 15").remove().unwrap();
+
+    thread::sleep(Duration::from_millis(500));
+
+    assert!(fs::metadata(&data_path).unwrap().len() * 3 < size);
+    assert!(!PATH.join("Page.compacting").exists());
+
+    arthas::load::<Page>().unwrap();
+    assert_eq!(Page::session().count().unwrap(), 5);
+}
@@ -0,0 +1,44 @@
+#[macro_use]
+extern crate serde_derive;
+#[macro_use]
+extern crate arthas_derive;
+extern crate rand;
+extern crate arthas;
+extern crate env_logger;
+extern crate mktemp;
+
+pub mod common;
+pub mod model;
+
+use model::*;
+use mktemp::Temp;
+use std::fs;
+
+
+#[test]
+fn test_compact() {
+    let path = Temp::new_dir().unwrap().to_path_buf();
+    arthas::config::path(&path);
+    arthas::config::compaction_threshold(1.0);
+
+    for i in 0..20 {
+        Article::session().insert(Article::new("Foobar!").views(i).content("x".repeat(100))).unwrap();
+    }
+
+    Article::session().field("views").lt(15).remove().unwrap();
+    arthas::load::<Article>().unwrap();
+    assert_eq!(Article::session().count().unwrap(), 5);
+
+    let data_path = path.join("Article.ar");
+    let size = fs::metadata(&data_path).unwrap().len();
+    arthas::compact::<Article>().unwrap();
+
+    assert!(fs::metadata(&data_path).unwrap().len() * 3 < size);
+    assert!(path.join("Article.arx").is_file());
+    assert!(!path.join("Article.compacting").exists());
+    assert!(!path.join("Article.saving").exists());
+
+    arthas::load::<Article>().unwrap();
+    assert_eq!(Article::session().count().unwrap(), 5);
+    assert_eq!(Article::session().field("views").ge(15).count().unwrap(), 5);
+}
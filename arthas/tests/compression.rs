@@ -0,0 +1,88 @@
+#[macro_use]
+extern crate serde_derive;
+#[macro_use]
+extern crate arthas_derive;
+extern crate rand;
+extern crate arthas;
+extern crate env_logger;
+extern crate mktemp;
+
+pub mod common;
+pub mod model;
+
+use model::*;
+use mktemp::Temp;
+use std::fs;
+use std::path::Path;
+
+
+#[test]
+fn test_compression() {
+    let temp = Temp::new_dir().unwrap();
+    let path = temp.to_path_buf();
+    arthas::config::path(&path);
+
+    let content = "Arthas is an in-memory structure database. ".repeat(50);
+
+    for i in 0..10 {
+        Page::session().insert(Page::new("Foobar!", &content)).unwrap();
+        Article::session().insert(Article::new("Foobar!").views(i).content(content.clone())).unwrap();
+    }
+
+    arthas::load::<Page>().unwrap();
+    arthas::load::<Article>().unwrap();
+
+    let size = len(path.join("Article.ar"));
+    assert!(len(path.join("Page.ar")) * 5 < size);
+
+    arthas::config::compression(true);
+
+    for i in 10..20 {
+        Article::session().insert(Article::new("Foobar!").views(i).content(content.clone())).unwrap();
+    }
+
+    arthas::load::<Article>().unwrap();
+    assert!(len(path.join("Article.ar")) < size + size / 5);
+    reload(&content, 20);
+
+    arthas::compact::<Article>().unwrap();
+    let compacted_size = len(path.join("Article.ar"));
+    assert!(compacted_size * 5 < size);
+    reload(&content, 20);
+
+    arthas::config::compression(false);
+
+    for i in 20..30 {
+        Article::session().insert(Article::new("Foobar!").views(i).content(content.clone())).unwrap();
+    }
+
+    Article::session()
+        .field("views")
+        .lt(5)
+        .update(|article| article.title = "Updated!".to_owned())
+        .unwrap();
+
+    arthas::load::<Article>().unwrap();
+    assert!(len(path.join("Article.ar")) > compacted_size + size / 2);
+    reload(&content, 30);
+    assert_eq!(Article::session().field("title").eq("Updated!").count().unwrap(), 5);
+}
+
+fn reload(content: &str, count: usize) {
+    arthas::config::persistence(false);
+    Article::session().remove().unwrap();
+    Page::session().remove().unwrap();
+    arthas::config::persistence(true);
+
+    arthas::load::<Page>().unwrap();
+    arthas::load::<Article>().unwrap();
+
+    let articles = Article::session().find().unwrap();
+    assert_eq!(articles.len(), count);
+    assert!(articles.iter().all(|article| article.content == content));
+    assert_eq!(Page::session().field("content").eq(content).count().unwrap(), 10);
+}
+
+fn len<P: AsRef<Path>>(path: P) -> u64 {
+    fs::metadata(path).unwrap().len()
+}
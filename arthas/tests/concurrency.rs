@@ -3,18 +3,56 @@
 extern crate serde_derive;
 #[macro_use]
 extern crate arthas_derive;
+#[macro_use]
+extern crate lazy_static;
 extern crate rand;
 extern crate arthas;
 extern crate env_logger;
+extern crate serde;
 
 pub mod common;
 pub mod model;
 
 use model::*;
 use std::thread::spawn;
+use std::time::{Duration, Instant};
+use std::sync::Mutex;
+use std::sync::mpsc::{channel, Sender, Receiver};
+use serde::{Deserialize, Deserializer};
 use common::setup;
 
 
+lazy_static! {
+    /// Decoding a title waits once while a pause is set, see `pause`.
+    static ref PAUSE: Mutex<Option<(Sender<()>, Receiver<()>)>> = Mutex::new(None);
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize, Arthas)]
+pub struct PausedNote {
+    pub _id: String,
+    #[serde(deserialize_with = "pause")]
+    pub title: String,
+}
+
+impl PausedNote {
+    fn new(title: &str) -> PausedNote {
+        PausedNote { title: title.to_owned(), ..Default::default() }
+    }
+}
+
+/// Tell the test the decode started, then wait until it resumes the decode.
+fn pause<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    let title = String::deserialize(deserializer)?;
+
+    if let Some((started, resume)) = PAUSE.lock().unwrap().take() {
+        started.send(()).unwrap();
+        resume.recv().unwrap();
+    }
+
+    Ok(title)
+}
+
+
 #[test]
 fn test_concurrent() {
     setup();
@@ -35,6 +73,135 @@ fn test_concurrent() {
     }
 }
 
+#[test]
+fn test_insert_during_find() {
+    setup();
+
+    PausedNote::session().insert(PausedNote::new("first")).unwrap();
+
+    let (started_sender, started) = channel();
+    let (resume, resume_receiver) = channel();
+    *PAUSE.lock().unwrap() = Some((started_sender, resume_receiver));
+
+    let finder = spawn(|| PausedNote::session().find().unwrap());
+    started.recv_timeout(Duration::from_secs(10)).unwrap();
+
+    let (inserted_sender, inserted) = channel();
+    spawn(move || {
+        PausedNote::session().insert(PausedNote::new("second")).unwrap();
+        inserted_sender.send(()).unwrap();
+    });
+
+    inserted.recv_timeout(Duration::from_secs(10)).expect("insert waited for find");
+    assert_eq!(PausedNote::session().count().unwrap(), 2);
+
+    resume.send(()).unwrap();
+    let titles = finder.join().unwrap().into_iter().map(|note| note.title).collect::<Vec<_>>();
+    assert_eq!(titles, vec!["first"]);
+}
+
+#[test]
+fn test_concurrent_insert_throughput() {
+    setup();
+
+    let total = 2000;
+    let thread_count = 4;
+
+    let single = insert_posts("single", total);
+
+    let started = Instant::now();
+    let mut threads = Vec::new();
+
+    for i in 0..thread_count {
+        threads.push(spawn(move || {
+            insert_posts(&format!("thread {}", i), total / thread_count);
+        }));
+    }
+
+    for thread in threads {
+        thread.join().unwrap();
+    }
+
+    let concurrent = started.elapsed();
+
+    for i in 0..thread_count {
+        let count = Post::session()
+            .field("author")
+            .eq(format!("thread {}", i))
+            .count()
+            .unwrap();
+        assert_eq!(count, total / thread_count);
+    }
+
+    assert!(to_millis(concurrent) <= to_millis(single) * 2,
+            "single: {:?}, concurrent: {:?}",
+            single,
+            concurrent);
+}
+
+#[test]
+fn test_writers_of_other_shards() {
+    setup();
+
+    let others = 16;
+    Post::session().insert(Post { _id: "blocked".to_owned(), ..Post::new("blocked", 0) }).unwrap();
+
+    let (entered_sender, entered) = channel();
+    let (resume, resume_receiver) = channel();
+    let updater = spawn(move || {
+        Post::session()
+            .id("blocked")
+            .update(move |post| {
+                entered_sender.send(()).unwrap();
+                resume_receiver.recv().unwrap();
+                post.title = "Updated!".to_owned();
+            })
+            .unwrap()
+    });
+    entered.recv_timeout(Duration::from_secs(10)).unwrap();
+
+    // Ids are spread over the shards by their hash, so some of them are not in the shard of
+    // the blocked update.
+    let (inserted_sender, inserted) = channel();
+
+    for i in 0..others {
+        let inserted_sender = inserted_sender.clone();
+        spawn(move || {
+            Post::session()
+                .insert(Post { _id: format!("other {}", i), ..Post::new("other", i) })
+                .unwrap();
+            inserted_sender.send(()).unwrap();
+        });
+    }
+
+    inserted.recv_timeout(Duration::from_secs(10)).expect("every insert waited for the update");
+
+    resume.send(()).unwrap();
+    assert_eq!(updater.join().unwrap(), 1);
+
+    for _ in 1..others {
+        inserted.recv_timeout(Duration::from_secs(10)).unwrap();
+    }
+
+    let post = Post::session().id("blocked").find_one().unwrap().unwrap();
+    assert_eq!(post.title, "Updated!");
+    assert_eq!(Post::session().field("author").eq("other").count().unwrap(), others);
+}
+
+fn insert_posts(author: &str, count: usize) -> Duration {
+    let started = Instant::now();
+
+    for i in 0..count {
+        Post::session().insert(Post::new(author, i)).unwrap();
+    }
+
+    started.elapsed()
+}
+
+fn to_millis(duration: Duration) -> u64 {
+    duration.as_secs() * 1000 + duration.subsec_nanos() as u64 / 1_000_000
+}
+
 fn insert() {
     let id = Article::session().insert(Article::new("Hello world!")).unwrap();
     let item = Article::session().id(&id).find_one().unwrap();
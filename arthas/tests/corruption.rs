@@ -0,0 +1,61 @@
+#[macro_use]
+extern crate serde_derive;
+#[macro_use]
+extern crate arthas_derive;
+extern crate rand;
+extern crate arthas;
+extern crate env_logger;
+extern crate mktemp;
+
+pub mod common;
+pub mod model;
+
+use model::*;
+use mktemp::Temp;
+use arthas::Error;
+use arthas::config::Corruption;
+use std::fs::OpenOptions;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+
+#[test]
+fn test_corrupt_record() {
+    let path = Temp::new_dir().unwrap().to_path_buf();
+    arthas::config::path(&path);
+
+    for i in 0..5 {
+        Article::session().insert(Article::new("Foobar!").views(i)).unwrap();
+    }
+
+    arthas::load::<Article>().unwrap();
+    flip_byte(path.join("Article.ar"), 30);
+
+    match arthas::load::<Article>() {
+        Err(Error::Corrupt(..)) => (),
+        result => panic!("expected a corrupt error, got {:?}", result),
+    }
+
+    arthas::config::corruption(Corruption::Quarantine);
+    let report = arthas::load::<Article>().unwrap();
+    assert_eq!(report.corrupt.len(), 1);
+    assert!(path.join("Article.quarantine").join(&report.corrupt[0]).is_file());
+
+    assert!(arthas::load::<Article>().unwrap().corrupt.is_empty());
+
+    flip_byte(path.join("Article.arx"), 10);
+
+    match arthas::load::<Article>() {
+        Err(Error::Corrupt(..)) => (),
+        result => panic!("expected a corrupt error, got {:?}", result),
+    }
+}
+
+fn flip_byte<P: AsRef<Path>>(path: P, offset: u64) {
+    let mut file = OpenOptions::new().read(true).write(true).open(path).unwrap();
+    let mut byte = [0];
+    file.seek(SeekFrom::Start(offset)).unwrap();
+    file.read_exact(&mut byte).unwrap();
+    file.seek(SeekFrom::Start(offset)).unwrap();
+    file.write_all(&[!byte[0]]).unwrap();
+}
@@ -0,0 +1,76 @@
+#[macro_use]
+extern crate serde_derive;
+#[macro_use]
+extern crate arthas_derive;
+extern crate rand;
+extern crate arthas;
+extern crate env_logger;
+extern crate mktemp;
+
+pub mod common;
+pub mod model;
+
+use model::*;
+use mktemp::Temp;
+use std::env;
+use std::fs;
+use std::process::{Command, Stdio};
+
+const STEPS: &'static [&'static str] = &["log:data",
+                                         "index:saving",
+                                         "log:index",
+                                         "compact:data",
+                                         "compact:saving",
+                                         "compact:remove",
+                                         "compact:rename"];
+
+
+#[test]
+fn test_crash_recovery() {
+    if let Ok(dir) = env::var("ARTHAS_CRASH_DIR") {
+        return write_until_crash(dir);
+    }
+
+    let temp = Temp::new_dir().unwrap();
+    arthas::config::persistence(false);
+
+    for step in STEPS {
+        let dir = temp.to_path_buf().join(step.replace(':', "-"));
+        fs::create_dir_all(&dir).unwrap();
+
+        let status = Command::new(env::current_exe().unwrap())
+            .args(&["test_crash_recovery", "--exact", "--nocapture"])
+            .env("ARTHAS_CRASH_DIR", &dir)
+            .env("ARTHAS_CRASH_POINT", step)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .unwrap();
+        assert!(!status.success(), "{} was not reached", step);
+
+        Article::session().remove().unwrap();
+        arthas::config::path(&dir);
+        arthas::load::<Article>().unwrap();
+
+        assert_eq!(Article::session().count().unwrap(), 4, "recover from {}", step);
+        assert_eq!(Article::session().field("views").ge(2).count().unwrap(), 4);
+        assert!(!dir.join("Article.saving").exists());
+        assert!(!dir.join("Article.compacting").exists());
+    }
+}
+
+/// Leave views 2 to 5, fold the log and compact, the crash point aborts on the way.
+fn write_until_crash(dir: String) {
+    arthas::config::path(dir);
+
+    for i in 0..5 {
+        Article::session().insert(Article::new("Foobar!").views(i)).unwrap();
+    }
+
+    Article::session().field("views").lt(2).remove().unwrap();
+    Article::session().insert(Article::new("Foobar!").views(5)).unwrap();
+
+    // Set only now, a remove of more than the threshold ratio checkpoints in the background.
+    arthas::config::compaction_threshold(0.0);
+    arthas::load::<Article>().unwrap();
+}
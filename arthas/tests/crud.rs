@@ -11,6 +11,7 @@ pub mod common;
 pub mod model;
 
 use model::*;
+use arthas::{Error, Event};
 use common::setup;
 
 
@@ -269,6 +270,39 @@ fn test_len() {
     assert_eq!(items.len(), 5);
 }
 
+#[test]
+fn test_order_without_condition() {
+    setup();
+
+    for (i, title) in ["c", "a", "e", "b", "d"].iter().enumerate() {
+        let mut track = Track::new(*title, i);
+        track.album = title.to_uppercase();
+        track.tags = (0..i).map(|tag| tag.to_string()).collect();
+        track.comments = vec![Comment { title: title.to_string(), ..Default::default() }];
+        Track::session().insert(track).unwrap();
+    }
+
+    let titles = |items: Vec<Track>| items.into_iter().map(|item| item.title).collect::<Vec<_>>();
+
+    let items = Track::session().asc("title").find().unwrap();
+    assert_eq!(titles(items), vec!["a", "b", "c", "d", "e"]);
+
+    let items = Track::session().desc("plays").limit(2).find().unwrap();
+    assert_eq!(titles(items), vec!["d", "b"]);
+
+    let items = Track::session().asc("album").offset(1).limit(2).find().unwrap();
+    assert_eq!(titles(items), vec!["b", "c"]);
+
+    let items = Track::session().desc("comments.[].title").find().unwrap();
+    assert_eq!(titles(items), vec!["e", "d", "c", "b", "a"]);
+
+    let items = Track::session().desc("len(tags)").find().unwrap();
+    assert_eq!(titles(items), vec!["d", "b", "e", "a", "c"]);
+
+    let items = Track::session().field("plays").lt(3).desc("len(tags)").find().unwrap();
+    assert_eq!(titles(items), vec!["e", "a", "c"]);
+}
+
 #[test]
 fn test_empty_query_all() {
     setup();
@@ -282,3 +316,354 @@ fn test_empty_query_all() {
 
     assert!(items.len() > 0);
 }
+
+#[test]
+fn test_no_index() {
+    setup();
+
+    for i in 0..5 {
+        let content = if i < 2 { "No Index A!" } else { "No Index B!" };
+        Article::session()
+            .insert(Article::new("No Index!").views(i).content(content))
+            .unwrap();
+    }
+
+    let items = Article::session()
+        .field("content")
+        .eq("No Index B!")
+        .desc("views")
+        .find()
+        .unwrap();
+
+    assert_eq!(items.len(), 3);
+    assert_eq!(items[0].views, 4);
+    assert_eq!(items[2].views, 2);
+
+    let count = Article::session()
+        .field("title")
+        .eq("No Index!")
+        .field("content")
+        .eq("No Index A!")
+        .count()
+        .unwrap();
+
+    assert_eq!(count, 2);
+
+    let count = Article::session()
+        .len("content")
+        .eq(11)
+        .field("views")
+        .lt(3)
+        .count()
+        .unwrap();
+
+    assert_eq!(count, 3);
+}
+
+#[test]
+fn test_compound_index() {
+    setup();
+
+    for i in 0..10 {
+        Post::session().insert(Post::new("Compound A!", i)).unwrap();
+        Post::session().insert(Post::new("Compound B!", i)).unwrap();
+    }
+
+    let items = Post::session()
+        .field("author")
+        .eq("Compound A!")
+        .field("created")
+        .gt(5)
+        .desc("created")
+        .find()
+        .unwrap();
+
+    assert_eq!(items.len(), 4);
+    assert!(items.iter().all(|item| item.author == "Compound A!"));
+    assert_eq!(items[0].created, 9);
+    assert_eq!(items[3].created, 6);
+
+    let items = Post::session()
+        .field("author")
+        .eq("Compound B!")
+        .field("created")
+        .ge(2)
+        .field("created")
+        .lt(5)
+        .asc("created")
+        .find()
+        .unwrap();
+
+    assert_eq!(items.len(), 3);
+    assert_eq!(items[0].created, 2);
+    assert_eq!(items[2].created, 4);
+
+    let items = Post::session()
+        .field("author")
+        .eq("Compound B!")
+        .asc("created")
+        .offset(1)
+        .limit(2)
+        .find()
+        .unwrap();
+
+    assert_eq!(items.len(), 2);
+    assert_eq!(items[0].created, 1);
+    assert_eq!(items[1].created, 2);
+
+    let count = Post::session()
+        .field("author")
+        .eq("Compound A!")
+        .field("created")
+        .eq(3)
+        .count()
+        .unwrap();
+
+    assert_eq!(count, 1);
+}
+
+#[test]
+fn test_hash_index() {
+    setup();
+
+    for i in 0..10 {
+        let name = if i % 2 == 0 { "Hash Even!" } else { "Hash Odd!" };
+        Account::session().insert(Account::new(1000 + i, name)).unwrap();
+    }
+
+    let items = Account::session()
+        .field("external_id")
+        .eq(1003)
+        .find()
+        .unwrap();
+
+    assert_eq!(items.len(), 1);
+    assert_eq!(items[0].name, "Hash Odd!");
+
+    let count = Account::session()
+        .field("external_id")
+        .eq(1004)
+        .field("name")
+        .eq("Hash Odd!")
+        .count()
+        .unwrap();
+
+    assert_eq!(count, 0);
+
+    let count = Account::session()
+        .field("external_id")
+        .ge(1005)
+        .field("name")
+        .eq("Hash Even!")
+        .count()
+        .unwrap();
+
+    assert_eq!(count, 2);
+
+    Account::session()
+        .field("external_id")
+        .eq(1003)
+        .update(|item| item.external_id = 2003)
+        .unwrap();
+
+    assert_eq!(Account::session().field("external_id").eq(1003).count().unwrap(),
+               0);
+    assert_eq!(Account::session().field("external_id").eq(2003).count().unwrap(),
+               1);
+
+    Account::session().field("external_id").eq(2003).remove().unwrap();
+
+    assert_eq!(Account::session().field("external_id").eq(2003).count().unwrap(),
+               0);
+}
+
+#[test]
+fn test_large_integer() {
+    setup();
+
+    let base = 1u64 << 53;
+
+    for i in 0..4 {
+        Ledger::session().insert(Ledger::new(base + i, -(base as i64) - i as i64)).unwrap();
+    }
+
+    Ledger::session().insert(Ledger::new(u64::max_value(), i64::min_value())).unwrap();
+    Ledger::session().insert(Ledger::new(u64::max_value() - 1, i64::max_value())).unwrap();
+
+    let items = Ledger::session().field("serial").eq(base + 1).find().unwrap();
+    assert_eq!(items.len(), 1);
+    assert_eq!(items[0].serial, base + 1);
+
+    assert_eq!(Ledger::session().field("serial").gt(base + 1).count().unwrap(), 4);
+    assert_eq!(Ledger::session().field("serial").le(base + 2).count().unwrap(), 3);
+    assert_eq!(Ledger::session().field("serial").ne(base).count().unwrap(), 5);
+    assert_eq!(Ledger::session().field("serial").eq(base as f64).count().unwrap(), 1);
+    assert_eq!(Ledger::session().field("serial").gt(base as f64 + 0.5).count().unwrap(), 5);
+
+    let items = Ledger::session()
+        .field("serial")
+        .lt(u64::max_value())
+        .desc("serial")
+        .find()
+        .unwrap();
+    let serials = items.iter().map(|item| item.serial).collect::<Vec<_>>();
+    assert_eq!(serials,
+               vec![u64::max_value() - 1, base + 3, base + 2, base + 1, base]);
+
+    let items = Ledger::session().asc("balance").limit(3).find().unwrap();
+    let balances = items.iter().map(|item| item.balance).collect::<Vec<_>>();
+    assert_eq!(balances,
+               vec![i64::min_value(), -(base as i64) - 3, -(base as i64) - 2]);
+
+    assert_eq!(Ledger::session().field("balance").lt(-(base as i64) - 1).count().unwrap(),
+               3);
+    assert_eq!(Ledger::session().field("balance").gt(-1).count().unwrap(), 1);
+
+    let external_id = u64::max_value() - 1;
+    Account::session().insert(Account::new(external_id, "Large Hash!")).unwrap();
+    Account::session().insert(Account::new(external_id - 1, "Large Hash!")).unwrap();
+
+    let items = Account::session().field("external_id").eq(external_id).find().unwrap();
+    assert_eq!(items.len(), 1);
+    assert_eq!(items[0].external_id, external_id);
+}
+
+#[test]
+fn test_collation() {
+    setup();
+
+    let contacts = [("Zebra@example.com", "Zebra", "Paris"),
+                    ("apple@example.com", "apple", "paris"),
+                    ("EMILE@example.com", "Émile", "Lyon"),
+                    ("eve@example.com", "eve", "Lyon"),
+                    ("odon@Example.com", "Ödön", "Nice"),
+                    ("zelie@example.com", "Ze\u{301}lie", "Nice"),
+                    ("anh@example.com", "Ắnh", "Hanoi")];
+
+    for &(email, name, city) in &contacts {
+        Contact::session().insert(Contact::new(email, name, city)).unwrap();
+    }
+
+    let names = |items: Vec<Contact>| items.into_iter().map(|item| item.name).collect::<Vec<_>>();
+
+    let items = Contact::session().asc("name").find().unwrap();
+    assert_eq!(names(items),
+               vec!["Ắnh", "apple", "Émile", "eve", "Ödön", "Zebra", "Ze\u{301}lie"]);
+
+    let items = Contact::session()
+        .field("name")
+        .ge("e")
+        .field("name")
+        .lt("f")
+        .desc("name")
+        .find()
+        .unwrap();
+    assert_eq!(names(items), vec!["eve", "Émile"]);
+
+    assert_eq!(Contact::session().field("name").eq("eve").count().unwrap(), 1);
+    assert_eq!(Contact::session().field("name").eq("Eve").count().unwrap(), 0);
+    assert_eq!(Contact::session().field("name").ieq("ÉMILE").count().unwrap(), 1);
+    assert_eq!(Contact::session().field("name").eq("Zélie").count().unwrap(), 1);
+    assert_eq!(Contact::session().field("name").ieq("ZÉLIE").count().unwrap(), 1);
+
+    let items = Contact::session().field("email").eq("ODON@EXAMPLE.COM").find().unwrap();
+    assert_eq!(names(items), vec!["Ödön"]);
+
+    assert_eq!(Contact::session().insert(Contact::new("Apple@Example.com", "apple", "Paris")),
+               Err(Error::DuplicateKey {
+                   field: "email".to_owned(),
+                   value: arthas::to_value("Apple@Example.com"),
+               }));
+
+    assert_eq!(Contact::session().field("city").eq("paris").count().unwrap(), 1);
+    assert_eq!(Contact::session().field("city").ieq("paris").count().unwrap(), 2);
+    let count = Contact::session()
+        .field("city")
+        .ieq("LYON")
+        .field("name")
+        .lt("f")
+        .count()
+        .unwrap();
+
+    assert_eq!(count, 2);
+}
+
+#[test]
+fn test_text() {
+    setup();
+
+    let notes = [("Rust databases", "Storing data in Rust is easy.", "fengcen"),
+                 ("Cooking", "A recipe for bread.", "fengcen"),
+                 ("Embedded database", "An in-memory database written in Rust.", "bob"),
+                 ("Rust", "The Rust language.", "bob")];
+
+    for &(title, body, author) in &notes {
+        Note::session().insert(Note::new(title, body, author)).unwrap();
+    }
+
+    let titles = |items: Vec<Note>| items.into_iter().map(|item| item.title).collect::<Vec<_>>();
+
+    let items = Note::session().text("DATABASE").find().unwrap();
+    assert_eq!(titles(items), vec!["Embedded database", "Rust databases"]);
+
+    let items = Note::session().text("rust database").limit(1).find().unwrap();
+    assert_eq!(titles(items), vec!["Embedded database"]);
+
+    assert_eq!(Note::session().text("rust database").count().unwrap(), 3);
+    assert_eq!(Note::session().text("stored").count().unwrap(), 1);
+    assert_eq!(Note::session().text("python").count().unwrap(), 0);
+
+    let items = Note::session()
+        .text("rust")
+        .field("author")
+        .eq("bob")
+        .asc("title")
+        .find()
+        .unwrap();
+
+    assert_eq!(titles(items), vec!["Embedded database", "Rust"]);
+
+    Note::session().field("title").eq("Embedded database").remove().unwrap();
+    Note::session()
+        .field("title")
+        .eq("Cooking")
+        .update(|note| note.body = "Bread and a database of recipes.".to_owned())
+        .unwrap();
+
+    let items = Note::session().text("database").find().unwrap();
+    assert_eq!(titles(items), vec!["Rust databases", "Cooking"]);
+
+    assert_eq!(Article::session().text("rust").find(), Err(Error::FieldNotFound));
+}
+
+#[test]
+fn test_watch() {
+    setup();
+
+    let receiver = Article::session().field("title").eq("Watch!").watch().unwrap();
+
+    let id = Article::session().insert(Article::new("Watch!").views(1)).unwrap();
+    Article::session().insert(Article::new("Not Watched!")).unwrap();
+    Article::session().id(&id).update(|item| item.views = 2).unwrap();
+    Article::session().id(&id).remove().unwrap();
+
+    match receiver.try_recv().unwrap() {
+        Event::Inserted(item) => assert_eq!((item._id, item.views), (id.clone(), 1)),
+        event => panic!("unexpected event: {:?}", event),
+    }
+
+    match receiver.try_recv().unwrap() {
+        Event::Updated { before, after } => {
+            assert_eq!(before.views, 1);
+            assert_eq!(after.views, 2);
+        }
+        event => panic!("unexpected event: {:?}", event),
+    }
+
+    match receiver.try_recv().unwrap() {
+        Event::Removed(item) => assert_eq!((item._id, item.views), (id, 2)),
+        event => panic!("unexpected event: {:?}", event),
+    }
+
+    assert!(receiver.try_recv().is_err());
+}
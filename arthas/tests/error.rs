@@ -28,4 +28,40 @@ fn test_field_not_found() {
 
     assert_eq!(Article::session().field("bad field").eq("bad value").find(),
                Err(Error::FieldNotFound));
+
+    assert_eq!(Article::session().asc("bad field").find(),
+               Err(Error::FieldNotFound));
+}
+
+#[test]
+fn test_duplicate_key() {
+    setup();
+
+    User::session().insert(User::new("foo@example.com")).unwrap();
+
+    assert_eq!(User::session().insert(User::new("foo@example.com")),
+               Err(Error::DuplicateKey {
+                   field: "email".to_owned(),
+                   value: arthas::to_value("foo@example.com"),
+               }));
+
+    let id = User::session().insert(User::new("bar@example.com")).unwrap();
+
+    assert_eq!(User::session().id(&id).update(|user| user.email = "foo@example.com".to_owned()),
+               Err(Error::DuplicateKey {
+                   field: "email".to_owned(),
+                   value: arthas::to_value("foo@example.com"),
+               }));
+
+    assert_eq!(User::session().id(&id).replace(User::new("foo@example.com")),
+               Err(Error::DuplicateKey {
+                   field: "email".to_owned(),
+                   value: arthas::to_value("foo@example.com"),
+               }));
+
+    let item = User::session().id(&id).find_one().unwrap();
+    assert_eq!(item.unwrap().email, "bar@example.com");
+
+    User::session().id(&id).update(|user| user.name = "Bar".to_owned()).unwrap();
+    User::session().id(&id).replace(User::new("bar@example.com")).unwrap();
 }
@@ -0,0 +1,98 @@
+#[macro_use]
+extern crate serde_derive;
+#[macro_use]
+extern crate arthas_derive;
+extern crate rand;
+extern crate arthas;
+extern crate env_logger;
+extern crate mktemp;
+#[macro_use]
+extern crate serde_json;
+
+pub mod common;
+pub mod model;
+
+use model::*;
+use mktemp::Temp;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::collections::HashMap;
+use std::path::Path;
+use arthas::{to_value, Error};
+use arthas::encoder::{bin_encode, encode_wrapper};
+
+
+#[test]
+fn test_format() {
+    let temp = Temp::new_dir().unwrap();
+    let path = temp.to_path_buf();
+    arthas::config::path(&path);
+
+    let field_int_map = Article::get_field_int_map();
+    let mut data = Vec::new();
+    let mut metas = HashMap::new();
+
+    for i in 0..5 {
+        let id = format!("legacy-{}", i);
+        let mut article = Article::new("Foobar!").views(i);
+        article._id = id.clone();
+
+        let value = encode_wrapper(&to_value(wrapper(id.clone(), article)), &field_int_map);
+        let record = bin_encode(value.to_string());
+        metas.insert(id, (data.len(), record.len(), record.len()));
+        data.extend_from_slice(&record);
+    }
+
+    let index = json!({
+        "struct_name": "Article",
+        "field_int_map": field_int_map,
+        "metas": metas,
+        "deleted": [],
+        "size": data.len(),
+    });
+
+    File::create(path.join("Article.ar")).unwrap().write_all(&data).unwrap();
+    File::create(path.join("Article.arx"))
+        .unwrap()
+        .write_all(&bin_encode(index.to_string()))
+        .unwrap();
+
+    arthas::load::<Article>().unwrap();
+    assert_eq!(Article::session().count().unwrap(), 5);
+    assert_eq!(Article::session().field("views").ge(3).count().unwrap(), 2);
+    assert_eq!(Article::session().id("legacy-4").find_one().unwrap().unwrap().views, 4);
+
+    assert!(read(path.join("Article.arx")).starts_with(b"\x89ARX"));
+    assert!(read(path.join("Article.ar")).starts_with(b"\x89ARD"));
+    assert!(read(path.join("Article.arl")).starts_with(b"\x89ARL"));
+
+    arthas::load::<Article>().unwrap();
+    assert_eq!(Article::session().count().unwrap(), 5);
+    assert_eq!(Article::session().id("legacy-2").find_one().unwrap().unwrap().views, 2);
+
+    for &version in &[1, 3] {
+        let mut header = b"\x89ARX\r\n\x1a\n".to_vec();
+        header.push(version);
+        header.extend_from_slice(b"\x04Post");
+        header.extend_from_slice(&[0; 8]);
+        File::create(path.join("Post.arx")).unwrap().write_all(&header).unwrap();
+
+        match arthas::load::<Post>() {
+            Err(Error::UnsupportedFormat { found, expected: 2 }) if found == version as u64 => (),
+            result => panic!("expected an unsupported format error, got {:?}", result),
+        }
+    }
+}
+
+fn read<P: AsRef<Path>>(path: P) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    File::open(path).unwrap().read_to_end(&mut bytes).unwrap();
+    bytes
+}
+
+fn wrapper(id: String, article: Article) -> HashMap<&'static str, serde_json::Value> {
+    let mut wrapper = HashMap::new();
+    wrapper.insert("id", to_value(id));
+    wrapper.insert("item", to_value(article));
+    wrapper
+}
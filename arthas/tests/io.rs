@@ -0,0 +1,39 @@
+#[macro_use]
+extern crate serde_derive;
+#[macro_use]
+extern crate arthas_derive;
+extern crate rand;
+extern crate arthas;
+extern crate env_logger;
+extern crate mktemp;
+
+pub mod common;
+pub mod model;
+
+use model::*;
+use mktemp::Temp;
+use arthas::Error;
+use std::fs::{self, File};
+use std::io::Write;
+
+
+#[test]
+fn test_io_errors() {
+    let path = Temp::new_dir().unwrap().to_path_buf();
+    fs::create_dir_all(&path).unwrap();
+    let file_path = path.join("file");
+    File::create(&file_path).unwrap();
+
+    arthas::config::path(&file_path);
+    match Article::session().insert(Article::new("Foobar!")) {
+        Err(Error::Io(..)) => (),
+        result => panic!("expected an io error, got {:?}", result),
+    }
+
+    arthas::config::path(&path);
+    File::create(path.join("Article.arx")).unwrap().write_all(b"not an index").unwrap();
+    match arthas::load::<Article>() {
+        Err(Error::Corrupt(..)) => (),
+        result => panic!("expected a corrupt error, got {:?}", result),
+    }
+}
@@ -24,6 +24,7 @@ pub struct Comment {
 pub struct Article {
     pub _id: String,
     pub title: String,
+    #[arthas(no_index)]
     pub content: String,
     pub day_to_views: HashMap<String, usize>,
     pub views: usize,
@@ -61,3 +62,171 @@ pub struct Blog {
     pub articles: Articles,
     pub comments: Comments,
 }
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize, Arthas)]
+pub struct User {
+    pub _id: String,
+    #[arthas(unique)]
+    pub email: String,
+    pub name: String,
+}
+
+impl User {
+    pub fn new<T: Into<String>>(email: T) -> User {
+        User { email: email.into(), ..Default::default() }
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize, Arthas)]
+#[arthas(index = "author, created")]
+pub struct Post {
+    pub _id: String,
+    pub author: String,
+    pub created: usize,
+    pub title: String,
+}
+
+impl Post {
+    pub fn new<T: Into<String>>(author: T, created: usize) -> Post {
+        Post {
+            author: author.into(),
+            created: created,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize, Arthas)]
+pub struct Account {
+    pub _id: String,
+    #[arthas(index = "hash")]
+    pub external_id: u64,
+    pub name: String,
+}
+
+impl Account {
+    pub fn new<T: Into<String>>(external_id: u64, name: T) -> Account {
+        Account {
+            external_id: external_id,
+            name: name.into(),
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize, Arthas)]
+pub struct Track {
+    pub _id: String,
+    pub title: String,
+    pub plays: usize,
+    #[arthas(no_index)]
+    pub album: String,
+    pub tags: Vec<String>,
+    pub comments: Vec<Comment>,
+}
+
+impl Track {
+    pub fn new<T: Into<String>>(title: T, plays: usize) -> Track {
+        Track {
+            title: title.into(),
+            plays: plays,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize, Arthas)]
+pub struct Ledger {
+    pub _id: String,
+    pub serial: u64,
+    pub balance: i64,
+}
+
+impl Ledger {
+    pub fn new(serial: u64, balance: i64) -> Ledger {
+        Ledger {
+            serial: serial,
+            balance: balance,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize, Arthas)]
+pub struct Contact {
+    pub _id: String,
+    #[arthas(unique, collate = "nocase")]
+    pub email: String,
+    #[arthas(collate = "unicode")]
+    pub name: String,
+    pub city: String,
+}
+
+impl Contact {
+    pub fn new<T: Into<String>>(email: T, name: T, city: T) -> Contact {
+        Contact {
+            email: email.into(),
+            name: name.into(),
+            city: city.into(),
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize, Arthas)]
+pub struct Note {
+    pub _id: String,
+    #[arthas(text)]
+    pub title: String,
+    #[arthas(text)]
+    pub body: String,
+    pub author: String,
+}
+
+impl Note {
+    pub fn new<T: Into<String>>(title: T, body: T, author: T) -> Note {
+        Note {
+            title: title.into(),
+            body: body.into(),
+            author: author.into(),
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize, Arthas)]
+pub struct Session {
+    pub _id: String,
+    pub token: String,
+    #[arthas(ttl)]
+    pub expires: u64,
+}
+
+impl Session {
+    pub fn new<T: Into<String>>(token: T, expires: u64) -> Session {
+        Session {
+            token: token.into(),
+            expires: expires,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize, Arthas)]
+#[arthas(compress)]
+pub struct Page {
+    pub _id: String,
+    pub title: String,
+    #[arthas(no_index)]
+    pub content: String,
+}
+
+impl Page {
+    pub fn new<T: Into<String>>(title: T, content: T) -> Page {
+        Page {
+            title: title.into(),
+            content: content.into(),
+            ..Default::default()
+        }
+    }
+}
@@ -12,6 +12,8 @@ pub mod model;
 
 use model::*;
 use common::setup;
+use std::thread;
+use std::time::Duration;
 
 
 #[test]
@@ -24,3 +26,38 @@ fn test_persistence_insert() {
             .unwrap();
     }
 }
+
+#[test]
+fn test_persistence_replace() {
+    setup();
+
+    let id = Note::session().insert(Note::new("Old title", "Old body", "Foo")).unwrap();
+    Note::session().id(&id).replace(Note::new("New title", "New body", "Foo")).unwrap();
+    arthas::load::<Note>().unwrap();
+
+    assert_eq!(Note::session().count().unwrap(), 1);
+    assert_eq!(Note::session().text("old").count().unwrap(), 0);
+    assert_eq!(Note::session().id(&id).find_one().unwrap().unwrap().title, "New title");
+}
+
+#[test]
+fn test_group_commit() {
+    setup();
+    arthas::config::durability(arthas::config::Durability::GroupCommit {
+        interval: Duration::from_millis(5),
+    });
+
+    let threads = (0..4)
+        .map(|i| {
+            thread::spawn(move || for j in 0..10 {
+                Account::session().insert(Account::new(i * 10 + j, "Foobar!")).unwrap();
+            })
+        })
+        .collect::<Vec<_>>();
+
+    for thread in threads {
+        thread.join().unwrap();
+    }
+
+    assert_eq!(Account::session().count().unwrap(), 40);
+}
@@ -0,0 +1,58 @@
+#[macro_use]
+extern crate serde_derive;
+#[macro_use]
+extern crate arthas_derive;
+extern crate rand;
+extern crate arthas;
+extern crate env_logger;
+extern crate mktemp;
+
+pub mod common;
+pub mod model;
+
+use model::*;
+use mktemp::Temp;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::Path;
+use std::cmp;
+
+
+#[test]
+fn test_torn_log() {
+    let temp = Temp::new_dir().unwrap();
+    let path = temp.to_path_buf();
+    arthas::config::path(&path);
+
+    for i in 0..3 {
+        Article::session().insert(Article::new("Foobar!").views(i)).unwrap();
+    }
+
+    let index = read(path.join("Article.arx"));
+    let log = read(path.join("Article.arl"));
+    let header = log.iter().position(|&byte| byte == b'{').unwrap();
+    arthas::config::persistence(false);
+
+    for len in 0..log.len() + 1 {
+        let dir = path.join(len.to_string());
+        fs::create_dir_all(&dir).unwrap();
+        File::create(dir.join("Article.arx")).unwrap().write_all(&index).unwrap();
+        File::create(dir.join("Article.arl")).unwrap().write_all(&log[..len]).unwrap();
+
+        Article::session().remove().unwrap();
+        arthas::config::path(&dir);
+        arthas::load::<Article>().unwrap();
+
+        // A line missing only its newline is complete, a torn header is written again.
+        let end = cmp::max(cmp::min(len + 1, log.len()), header);
+        let complete = log[header..end].iter().filter(|&&byte| byte == b'\n').count();
+        assert_eq!(Article::session().count().unwrap(), complete);
+        assert_eq!(fs::metadata(dir.join("Article.arl")).unwrap().len(), header as u64);
+    }
+}
+
+fn read<P: AsRef<Path>>(path: P) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    File::open(path).unwrap().read_to_end(&mut bytes).unwrap();
+    bytes
+}
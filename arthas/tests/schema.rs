@@ -0,0 +1,149 @@
+#[macro_use]
+extern crate serde_derive;
+#[macro_use]
+extern crate arthas_derive;
+#[macro_use]
+extern crate lazy_static;
+extern crate rand;
+extern crate arthas;
+extern crate env_logger;
+extern crate mktemp;
+
+pub mod common;
+
+use mktemp::Temp;
+use arthas::Error;
+use arthas::config::Corruption;
+use arthas::traits::Arthas;
+use std::fs::OpenOptions;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+
+mod old {
+    #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize, Arthas)]
+    pub struct Profile {
+        pub _id: String,
+        pub name: String,
+        pub visits: usize,
+        pub city: String,
+    }
+
+    #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize, Arthas)]
+    pub struct Visit {
+        pub _id: String,
+        pub page: String,
+    }
+}
+
+mod new {
+    #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize, Arthas)]
+    #[arthas(rename = "name=nickname")]
+    pub struct Profile {
+        pub _id: String,
+        pub nickname: String,
+        pub visits: usize,
+        pub email: String,
+    }
+
+    #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize, Arthas)]
+    pub struct Visit {
+        pub _id: String,
+        pub page: String,
+        pub referrer: String,
+    }
+}
+
+lazy_static! {
+    static ref PATH: PathBuf = {
+        let path = Temp::new_dir().unwrap().to_path_buf();
+        arthas::config::path(&path);
+        path
+    };
+}
+
+
+#[test]
+fn test_update_schema() {
+    let path = &*PATH;
+
+    let mut ids = Vec::new();
+    for i in 0..10 {
+        let profile = old::Profile {
+            name: format!("Foobar {}", i),
+            visits: i,
+            city: "Paris".to_owned(),
+            ..Default::default()
+        };
+        ids.push(old::Profile::session().insert(profile).unwrap());
+    }
+
+    old::Profile::session().id(&ids[9]).remove().unwrap();
+    arthas::load::<old::Profile>().unwrap();
+
+    arthas::load::<new::Profile>().unwrap();
+    assert!(path.join("Profile.ar.backup").is_file());
+    assert!(path.join("Profile.arx.backup").is_file());
+    check(&ids);
+
+    // The migrated records and index are saved, so a second load needs no migration.
+    arthas::load::<new::Profile>().unwrap();
+    check(&ids);
+
+    new::Profile::session()
+        .id(&ids[0])
+        .update(|profile| profile.email = "foobar@example.com".to_owned())
+        .unwrap();
+    arthas::load::<new::Profile>().unwrap();
+    assert_eq!(new::Profile::session().id(&ids[0]).find_one().unwrap().unwrap().email,
+               "foobar@example.com");
+}
+
+#[test]
+fn test_update_schema_with_corrupt_record() {
+    for i in 0..5 {
+        let visit = old::Visit { page: format!("/page/{}", i), ..Default::default() };
+        old::Visit::session().insert(visit).unwrap();
+    }
+
+    arthas::load::<old::Visit>().unwrap();
+    flip_byte(PATH.join("Visit.ar"), 30);
+
+    match arthas::load::<new::Visit>() {
+        Err(Error::Corrupt(..)) => (),
+        result => panic!("expected a corrupt error, got {:?}", result),
+    }
+
+    arthas::config::corruption(Corruption::Quarantine);
+    let report = arthas::load::<new::Visit>().unwrap();
+    assert_eq!(report.corrupt.len(), 1);
+    assert!(PATH.join("Visit.quarantine").join(&report.corrupt[0]).is_file());
+    assert_eq!(new::Visit::session().count().unwrap(), 4);
+
+    assert!(arthas::load::<new::Visit>().unwrap().corrupt.is_empty());
+    assert_eq!(new::Visit::session().count().unwrap(), 4);
+}
+
+fn check(ids: &[String]) {
+    let profiles = new::Profile::session().asc("visits").find().unwrap();
+    assert_eq!(profiles.len(), 9);
+
+    for (i, profile) in profiles.iter().enumerate() {
+        assert_eq!(profile._id, ids[i]);
+        assert_eq!(profile.nickname, format!("Foobar {}", i));
+        assert_eq!(profile.visits, i);
+        assert_eq!(profile.email, "");
+    }
+
+    assert_eq!(new::Profile::session().field("nickname").eq("Foobar 3").count().unwrap(),
+               1);
+}
+
+fn flip_byte<P: AsRef<Path>>(path: P, offset: u64) {
+    let mut file = OpenOptions::new().read(true).write(true).open(path).unwrap();
+    let mut byte = [0];
+    file.seek(SeekFrom::Start(offset)).unwrap();
+    file.read_exact(&mut byte).unwrap();
+    file.seek(SeekFrom::Start(offset)).unwrap();
+    file.write_all(&[!byte[0]]).unwrap();
+}
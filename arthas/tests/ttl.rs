@@ -0,0 +1,137 @@
+#[macro_use]
+extern crate serde_derive;
+#[macro_use]
+extern crate arthas_derive;
+#[macro_use]
+extern crate lazy_static;
+extern crate rand;
+extern crate arthas;
+extern crate env_logger;
+extern crate mktemp;
+
+pub mod common;
+pub mod model;
+
+use model::*;
+use mktemp::Temp;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use arthas::traits::Arthas;
+
+
+lazy_static! {
+    static ref PATH: PathBuf = {
+        let path = Temp::new_dir().unwrap().to_path_buf();
+        arthas::config::path(&path);
+        arthas::config::sweep_interval(Duration::from_millis(50));
+        // Sweeps do not checkpoint at once, so the log keeps their records.
+        arthas::config::compaction_threshold(1.0);
+        path
+    };
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize, Arthas)]
+pub struct Token {
+    pub _id: String,
+    #[arthas(ttl)]
+    pub expires: u64,
+}
+
+
+#[test]
+fn test_ttl() {
+    let path = &*PATH;
+    let now = get_now();
+    let expired = Session::session().insert(Session::new("expired", now - 10)).unwrap();
+    Session::session().insert(Session::new("live", now + 3600)).unwrap();
+    Session::session().insert(Session::new("forever", 0)).unwrap();
+
+    assert!(Session::session().id(&expired).find_one().unwrap().is_none());
+    assert_eq!(Session::session().field("token").eq("expired").count().unwrap(), 0);
+    assert_eq!(Session::session().field("expires").lt(now).count().unwrap(), 1);
+    assert_eq!(Session::session().asc("expires").find().unwrap().len(), 2);
+
+    let soon = Session::session().insert(Session::new("soon", now + 1)).unwrap();
+    assert_eq!(Session::session().count().unwrap(), 3);
+
+    thread::sleep(Duration::from_millis(2500));
+
+    assert_eq!(Session::session().count().unwrap(), 2);
+
+    let log = read_log(path, "Session");
+
+    for id in &[expired, soon] {
+        assert!(log.contains(&format!(r#"{{"action":"Delete","id":"{}","value":null}}"#, id)));
+    }
+}
+
+#[test]
+fn test_sweep_during_load() {
+    let path = &*PATH;
+
+    for _ in 0..1000 {
+        Article::session().insert(Article::new("Hello world!")).unwrap();
+    }
+
+    let loaded = Arc::new(AtomicBool::new(false));
+    let loader = {
+        let loaded = loaded.clone();
+        thread::spawn(move || {
+            for _ in 0..10 {
+                arthas::load::<Article>().unwrap();
+            }
+
+            loaded.store(true, Ordering::SeqCst);
+        })
+    };
+
+    // Tokens are swept as soon as they are inserted, while articles are loaded.
+    let mut ids = Vec::new();
+
+    while !loaded.load(Ordering::SeqCst) {
+        let token = Token { expires: get_now() - 10, ..Default::default() };
+        ids.push(Token::session().insert(token).unwrap());
+        thread::sleep(Duration::from_millis(5));
+    }
+
+    loader.join().unwrap();
+
+    // Expired tokens are not found by queries, so the log tells when they are swept.
+    let is_swept = |log: &str, id: &String| {
+        log.contains(&format!(r#"{{"action":"Delete","id":"{}","value":null}}"#, id))
+    };
+
+    for _ in 0..100 {
+        let log = read_log(path, "Token");
+        if ids.iter().all(|id| is_swept(&log, id)) {
+            break;
+        }
+
+        thread::sleep(Duration::from_millis(50));
+    }
+
+    let log = read_log(path, "Token");
+
+    for id in &ids {
+        assert!(log.contains(&format!(r#"{{"action":"Insert","id":"{}""#, id)));
+        assert!(is_swept(&log, id));
+    }
+}
+
+fn get_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()
+}
+
+fn read_log(path: &Path, struct_name: &str) -> String {
+    let mut bytes = Vec::new();
+    File::open(path.join(format!("{}.arl", struct_name)))
+        .unwrap()
+        .read_to_end(&mut bytes)
+        .unwrap();
+    String::from_utf8_lossy(&bytes).into_owned()
+}
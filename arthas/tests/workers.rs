@@ -0,0 +1,91 @@
+#[macro_use]
+extern crate serde_derive;
+#[macro_use]
+extern crate arthas_derive;
+extern crate rand;
+extern crate arthas;
+extern crate env_logger;
+extern crate mktemp;
+
+pub mod common;
+
+use mktemp::Temp;
+use std::fs::{self, File};
+use std::io::Read;
+use std::thread;
+use std::time::Duration;
+use arthas::traits::Arthas;
+
+
+const SWEEP: &'static str = "sweep Token";
+/// Thread names are cut to 15 bytes.
+const CHECKPOINT: &'static str = "checkpoint Toke";
+
+mod old {
+    #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize, Arthas)]
+    pub struct Token {
+        pub _id: String,
+        pub value: String,
+        #[arthas(ttl)]
+        pub expires: u64,
+    }
+}
+
+mod new {
+    #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize, Arthas)]
+    pub struct Token {
+        pub _id: String,
+        pub value: String,
+        pub scope: String,
+        #[arthas(ttl)]
+        pub expires: u64,
+    }
+}
+
+
+#[test]
+#[cfg(target_os = "linux")]
+fn test_workers() {
+    let temp = Temp::new_dir().unwrap();
+    arthas::config::path(temp.to_path_buf());
+    arthas::config::sweep_interval(Duration::from_millis(50));
+
+    arthas::load::<old::Token>().unwrap();
+    old::Token::session().insert(old::Token::default()).unwrap();
+    wait_threads(1);
+
+    // A memory rebuilt for other fields stops the workers of the one it replaces.
+    arthas::load::<new::Token>().unwrap();
+    wait_threads(1);
+
+    new::Token::session().insert(new::Token::default()).unwrap();
+    arthas::load::<new::Token>().unwrap();
+    thread::sleep(Duration::from_millis(200));
+    assert_eq!(count_threads(SWEEP), 1);
+    assert_eq!(count_threads(CHECKPOINT), 1);
+}
+
+fn wait_threads(count: usize) {
+    for _ in 0..100 {
+        if count_threads(SWEEP) == count && count_threads(CHECKPOINT) == count {
+            return;
+        }
+
+        thread::sleep(Duration::from_millis(50));
+    }
+
+    assert_eq!(count_threads(SWEEP), count);
+    assert_eq!(count_threads(CHECKPOINT), count);
+}
+
+fn count_threads(name: &str) -> usize {
+    fs::read_dir("/proc/self/task")
+        .unwrap()
+        .filter(|entry| {
+            let mut comm = String::new();
+            File::open(entry.as_ref().unwrap().path().join("comm"))
+                .and_then(|mut file| file.read_to_string(&mut comm))
+                .is_ok() && comm.trim() == name
+        })
+        .count()
+}
@@ -33,6 +33,7 @@ fn impl_arthas(ast: &DeriveInput) -> quote::Tokens {
     let name = &ast.ident;
     let name_string = name.to_string();
     let is_one = check_is_one(ast);
+    let is_compressed = check_is_compressed(ast);
     let has_id = check_has_id(ast);
 
     let set_id_block = if has_id {
@@ -48,6 +49,13 @@ fn impl_arthas(ast: &DeriveInput) -> quote::Tokens {
     };
 
     let field_int_map_block = generate_field_int_map_block(ast);
+    let unique_fields = get_unique_fields(ast);
+    let index_fields = get_index_fields(ast);
+    let compound_indexes = generate_compound_indexes(ast);
+    let hash_index_fields = get_hash_index_fields(ast);
+    let (collate_fields, collations) = get_collate_fields(ast);
+    let text_fields = get_text_fields(ast);
+    let ttl_field = generate_ttl_field(ast);
     let deep_struct = generate_deep_struct(ast);
     let rename_map_block = generate_rename_map_block(ast);
 
@@ -71,6 +79,10 @@ fn impl_arthas(ast: &DeriveInput) -> quote::Tokens {
                 #is_one
             }
 
+            fn is_compressed() -> bool {
+                #is_compressed
+            }
+
             fn new_empty() -> Self {
                 #name {
                     ..Default::default()
@@ -101,6 +113,34 @@ fn impl_arthas(ast: &DeriveInput) -> quote::Tokens {
                 #rename_map_block
             }
 
+            fn get_unique_fields() -> Vec<String> {
+                vec![#(#unique_fields.to_owned()),*]
+            }
+
+            fn get_index_fields() -> Vec<String> {
+                vec![#(#index_fields.to_owned()),*]
+            }
+
+            fn get_compound_indexes() -> Vec<Vec<String>> {
+                vec![#(#compound_indexes),*]
+            }
+
+            fn get_hash_index_fields() -> Vec<String> {
+                vec![#(#hash_index_fields.to_owned()),*]
+            }
+
+            fn get_collate_fields() -> Vec<(String, String)> {
+                vec![#((#collate_fields.to_owned(), #collations.to_owned())),*]
+            }
+
+            fn get_text_fields() -> Vec<String> {
+                vec![#(#text_fields.to_owned()),*]
+            }
+
+            fn get_ttl_field() -> Option<(String, u64)> {
+                #ttl_field
+            }
+
             fn get_field_int_map() -> ::arthas::traits::FieldIntMap {
                 let mut field_int_map = ::std::collections::HashMap::new();
                 let field_type_map = Self::get_field_type_map();
@@ -220,15 +260,21 @@ fn generate_deep_struct(ast: &DeriveInput) -> quote::Tokens {
 }
 
 fn get_rename_value(ast: &DeriveInput) -> String {
-    for attr in &ast.attrs {
+    get_name_values(&ast.attrs, "rename").into_iter().next().unwrap_or_else(String::new)
+}
+
+fn get_name_values(attrs: &[syn::Attribute], name: &str) -> Vec<String> {
+    let mut values = Vec::new();
+
+    for attr in attrs {
         if let syn::MetaItem::List(ref ident, ref meta_items) = attr.value {
             if ident.to_string() == "arthas" {
                 for item in meta_items {
                     if let syn::NestedMetaItem::MetaItem(ref meta_item) = *item {
                         if let syn::MetaItem::NameValue(ref ident, ref lit) = *meta_item {
-                            if ident.to_string() == "rename" {
+                            if ident.to_string() == name {
                                 if let syn::Lit::Str(ref value, _) = *lit {
-                                    return value.to_owned();
+                                    values.push(value.to_owned());
                                 }
                             }
                         }
@@ -238,7 +284,7 @@ fn get_rename_value(ast: &DeriveInput) -> String {
         }
     }
 
-    String::new()
+    values
 }
 
 fn generate_rename_map_block(ast: &DeriveInput) -> quote::Tokens {
@@ -353,13 +399,140 @@ fn check_has_id(ast: &DeriveInput) -> bool {
 }
 
 fn check_is_one(ast: &DeriveInput) -> bool {
-    for attr in &ast.attrs {
+    has_word(&ast.attrs, "is_one")
+}
+
+fn check_is_compressed(ast: &DeriveInput) -> bool {
+    has_word(&ast.attrs, "compress")
+}
+
+fn get_unique_fields(ast: &DeriveInput) -> Vec<String> {
+    get_ast_struct_fields(ast)
+        .iter()
+        .filter(|field| has_word(&field.attrs, "unique"))
+        .map(|field| field.ident.clone().unwrap().to_string())
+        .collect()
+}
+
+fn get_index_fields(ast: &DeriveInput) -> Vec<String> {
+    let index_by_default = !has_word(&ast.attrs, "no_index");
+
+    get_ast_struct_fields(ast)
+        .iter()
+        .filter(|field| {
+            !is_hash_index(field) &&
+            (has_word(&field.attrs, "index") || has_word(&field.attrs, "unique") ||
+             index_by_default && !has_word(&field.attrs, "no_index"))
+        })
+        .map(|field| field.ident.clone().unwrap().to_string())
+        .collect()
+}
+
+fn get_hash_index_fields(ast: &DeriveInput) -> Vec<String> {
+    get_ast_struct_fields(ast)
+        .iter()
+        .filter(|field| is_hash_index(field))
+        .map(|field| field.ident.clone().unwrap().to_string())
+        .collect()
+}
+
+fn is_hash_index(field: &Field) -> bool {
+    let mut is_hash = false;
+
+    for kind in get_name_values(&field.attrs, "index") {
+        match kind.trim() {
+            "hash" => is_hash = true,
+            _ => panic!("unsupported index kind: {}", kind),
+        }
+    }
+
+    is_hash
+}
+
+fn get_text_fields(ast: &DeriveInput) -> Vec<String> {
+    get_ast_struct_fields(ast)
+        .iter()
+        .filter(|field| has_word(&field.attrs, "text"))
+        .map(|field| field.ident.clone().unwrap().to_string())
+        .collect()
+}
+
+fn generate_ttl_field(ast: &DeriveInput) -> quote::Tokens {
+    let mut ttl_fields = Vec::new();
+
+    for field in get_ast_struct_fields(ast) {
+        let name = field.ident.clone().unwrap().to_string();
+
+        if has_word(&field.attrs, "ttl") {
+            ttl_fields.push((name.clone(), 0));
+        }
+
+        for seconds in get_name_values(&field.attrs, "ttl") {
+            match seconds.trim().parse::<u64>() {
+                Ok(seconds) => ttl_fields.push((name.clone(), seconds)),
+                Err(_) => panic!("ttl requires seconds: {}", seconds),
+            }
+        }
+    }
+
+    match ttl_fields.len() {
+        0 => quote! { None },
+        1 => {
+            let (ref field, seconds) = ttl_fields[0];
+            quote! { Some((#field.to_owned(), #seconds)) }
+        }
+        _ => panic!("only one ttl field is supported"),
+    }
+}
+
+fn get_collate_fields(ast: &DeriveInput) -> (Vec<String>, Vec<String>) {
+    let mut fields = Vec::new();
+    let mut collations = Vec::new();
+
+    for field in get_ast_struct_fields(ast) {
+        for collation in get_name_values(&field.attrs, "collate") {
+            match collation.trim() {
+                "nocase" | "unicode" => {
+                    fields.push(field.ident.clone().unwrap().to_string());
+                    collations.push(collation.trim().to_owned());
+                }
+                _ => panic!("unsupported collation: {}", collation),
+            }
+        }
+    }
+
+    (fields, collations)
+}
+
+fn generate_compound_indexes(ast: &DeriveInput) -> Vec<quote::Tokens> {
+    let mut compound_indexes = Vec::new();
+
+    for index_value in get_name_values(&ast.attrs, "index") {
+        let fields = index_value.split(',')
+            .map(|field| field.trim())
+            .filter(|field| !field.is_empty())
+            .collect::<Vec<_>>();
+
+        if fields.len() < 2 {
+            panic!("compound index requires at least two fields");
+        }
+
+        compound_indexes.push(quote!{
+            vec![#(#fields.to_owned()),*]
+        });
+    }
+
+    compound_indexes
+}
+
+fn has_word(attrs: &[syn::Attribute], word: &str) -> bool {
+    for attr in attrs {
         if let syn::MetaItem::List(ref ident, ref meta_items) = attr.value {
             if ident.to_string() == "arthas" {
                 for item in meta_items {
                     if let syn::NestedMetaItem::MetaItem(ref meta_item) = *item {
                         if let syn::MetaItem::Word(ref ident) = *meta_item {
-                            if ident.to_string() == "is_one" {
+                            if ident.to_string() == word {
                                 return true;
                             }
                         }